@@ -0,0 +1,409 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, sysvar};
+use anchor_lang::{system_program, InstructionData};
+use anchor_spl::{associated_token, associated_token::get_associated_token_address, token};
+use gda::{accounts, instruction, ListingMode};
+
+use crate::pda::*;
+
+/// Curve and window of a new listing, see `gda::auctioneer_sell`.
+#[derive(Clone, Copy, Debug)]
+pub struct SellParams {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub start_price: u64,
+    pub reserve_price: u64,
+    pub decay_constant: u64,
+    pub scale_factor: u64,
+    pub mode: ListingMode,
+    pub emission_rate: u64,
+}
+
+/// New curve of a listing, see `gda::update_listing`.
+#[derive(Clone, Copy, Debug)]
+pub struct CurveUpdate {
+    pub start_price: u64,
+    pub reserve_price: u64,
+    pub decay_constant: u64,
+    pub scale_factor: u64,
+    pub end_time: i64,
+}
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: gda::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Lists `listing.token_size` units from the seller's `token_account`.
+pub fn auctioneer_sell(
+    listing: &ListingKeys,
+    token_account: Pubkey,
+    params: SellParams,
+) -> Instruction {
+    let ah = &listing.auction_house;
+    let listing_authority = listing.listing_authority().0;
+    build(
+        accounts::AuctioneerSell {
+            auction_house_program: mpl_auction_house::ID,
+            listing_config: listing.listing_config().0,
+            listing_authority,
+            token_account,
+            token_mint: listing.token_mint,
+            vault: listing.vault(),
+            listing_proceeds_account: listing.listing_proceeds_account(),
+            treasury_mint: ah.treasury_mint,
+            auction_house: ah.address,
+            auctioneer_authority: ah.auctioneer_authority().0,
+            ah_auctioneer_pda: ah.ah_auctioneer_pda(),
+            wallet: listing.seller,
+            system_program: system_program::ID,
+            token_program: token::ID,
+            ata_program: associated_token::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::AuctioneerSell {
+            token_size: listing.token_size,
+            start_time: params.start_time,
+            end_time: params.end_time,
+            start_price: params.start_price,
+            reserve_price: params.reserve_price,
+            decay_constant: params.decay_constant,
+            scale_factor: params.scale_factor,
+            mode: params.mode,
+            emission_rate: params.emission_rate,
+        },
+    )
+}
+
+fn place_order_accounts(
+    listing: &ListingKeys,
+    buyer: &Pubkey,
+    buyer_trade_state: Pubkey,
+) -> accounts::AuctioneerBuy {
+    let ah = &listing.auction_house;
+    accounts::AuctioneerBuy {
+        auction_house_program: mpl_auction_house::ID,
+        listing_config: listing.listing_config().0,
+        listing_authority: listing.listing_authority().0,
+        seller: listing.seller,
+        wallet: *buyer,
+        payment_account: ah.payment_account(buyer),
+        transfer_authority: *buyer,
+        treasury_mint: ah.treasury_mint,
+        token_account: listing.vault(),
+        metadata: listing.metadata(),
+        escrow_payment_account: ah.escrow_payment_account(buyer).0,
+        authority: ah.authority,
+        auctioneer_authority: ah.auctioneer_authority().0,
+        auction_house: ah.address,
+        auction_house_fee_account: ah.fee_account(),
+        buyer_trade_state,
+        ah_auctioneer_pda: ah.ah_auctioneer_pda(),
+        token_program: token::ID,
+        system_program: system_program::ID,
+        rent: sysvar::rent::ID,
+    }
+}
+
+/// Bids `max_total_price` on `order_size` units. The bid is settled at the curve price later on,
+/// so settle it with `auctioneer_execute_sale` and `buyer_price` set to `max_total_price`.
+pub fn place_order(
+    listing: &ListingKeys,
+    buyer: Pubkey,
+    order_size: u64,
+    max_total_price: u64,
+) -> Instruction {
+    let (buyer_trade_state, trade_state_bump) =
+        listing.buyer_trade_state(&buyer, max_total_price, order_size);
+    build(
+        place_order_accounts(listing, &buyer, buyer_trade_state),
+        instruction::PlaceOrder {
+            trade_state_bump,
+            escrow_payment_bump: listing.auction_house.escrow_payment_account(&buyer).1,
+            auctioneer_authority_bump: listing.auction_house.auctioneer_authority().1,
+            _token_size: listing.token_size,
+            order_size,
+            max_total_price,
+        },
+    )
+}
+
+/// Bids `budget` on as many units as it covers. The program computes that size itself, but the
+/// buyer trade state is derived from it, so `order_size` has to predict it: quote it with
+/// `ListingConfig::max_quantity_for_budget` at the time the transaction is expected to land.
+/// The instruction fails with `OrderSizeMismatch` if the price has moved the size since.
+pub fn place_order_with_budget(
+    listing: &ListingKeys,
+    buyer: Pubkey,
+    order_size: u64,
+    budget: u64,
+) -> Instruction {
+    let (buyer_trade_state, trade_state_bump) =
+        listing.buyer_trade_state(&buyer, budget, order_size);
+    build(
+        place_order_accounts(listing, &buyer, buyer_trade_state),
+        instruction::PlaceOrderWithBudget {
+            trade_state_bump,
+            escrow_payment_bump: listing.auction_house.escrow_payment_account(&buyer).1,
+            auctioneer_authority_bump: listing.auction_house.auctioneer_authority().1,
+            _token_size: listing.token_size,
+            budget,
+        },
+    )
+}
+
+/// Settles a bid of `buyer_price` at the curve price when the transaction lands, refunding the
+/// difference. Royalty creators go in `remaining_accounts`, as for Auction House.
+pub fn auctioneer_execute_sale(
+    listing: &ListingKeys,
+    buyer: Pubkey,
+    buyer_price: u64,
+    order_size: u64,
+    remaining_accounts: &[AccountMeta],
+) -> Instruction {
+    let ah = &listing.auction_house;
+    let (escrow_payment_account, escrow_payment_bump) = ah.escrow_payment_account(&buyer);
+    let (free_trade_state, free_trade_state_bump) = listing.free_trade_state(order_size);
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+    let (auctioneer_authority, auctioneer_authority_bump) = ah.auctioneer_authority();
+    let mut ix = build(
+        accounts::AuctioneerExecuteSale {
+            auction_house_program: mpl_auction_house::ID,
+            buyer,
+            listing_config: listing.listing_config().0,
+            listing_authority: listing.listing_authority().0,
+            seller: listing.seller,
+            token_account: listing.vault(),
+            token_mint: listing.token_mint,
+            metadata: listing.metadata(),
+            treasury_mint: ah.treasury_mint,
+            escrow_payment_account,
+            seller_payment_receipt_account: ah.payment_account(&listing.seller),
+            listing_proceeds_account: listing.listing_proceeds_account(),
+            buyer_receipt_token_account: get_associated_token_address(&buyer, &listing.token_mint),
+            buyer_refund_account: ah.payment_account(&buyer),
+            authority: ah.authority,
+            auctioneer_authority,
+            auction_house: ah.address,
+            auction_house_fee_account: ah.fee_account(),
+            auction_house_treasury: ah.treasury(),
+            buyer_trade_state: listing.buyer_trade_state(&buyer, buyer_price, order_size).0,
+            seller_trade_state: listing.seller_trade_state(order_size).0,
+            free_trade_state,
+            ah_auctioneer_pda: ah.ah_auctioneer_pda(),
+            token_program: token::ID,
+            system_program: system_program::ID,
+            ata_program: associated_token::ID,
+            program_as_signer,
+            rent: sysvar::rent::ID,
+        },
+        instruction::AuctioneerExecuteSale {
+            escrow_payment_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            auctioneer_authority_bump,
+            buyer_price,
+            token_size: listing.token_size,
+            order_size,
+        },
+    );
+    ix.accounts.extend_from_slice(remaining_accounts);
+    ix
+}
+
+/// Bids `max_total_price` and settles `order_size` units in one instruction, refunding what the
+/// bid is above the curve price, as for `auctioneer_execute_sale`.
+pub fn buy(
+    listing: &ListingKeys,
+    buyer: Pubkey,
+    order_size: u64,
+    max_total_price: u64,
+    remaining_accounts: &[AccountMeta],
+) -> Instruction {
+    let ah = &listing.auction_house;
+    let (escrow_payment_account, escrow_payment_bump) = ah.escrow_payment_account(&buyer);
+    let (buyer_trade_state, trade_state_bump) =
+        listing.buyer_trade_state(&buyer, max_total_price, order_size);
+    let (free_trade_state, free_trade_state_bump) = listing.free_trade_state(order_size);
+    let (program_as_signer, program_as_signer_bump) = find_program_as_signer_address();
+    let (auctioneer_authority, auctioneer_authority_bump) = ah.auctioneer_authority();
+    let mut ix = build(
+        accounts::Buy {
+            auction_house_program: mpl_auction_house::ID,
+            listing_config: listing.listing_config().0,
+            listing_authority: listing.listing_authority().0,
+            seller: listing.seller,
+            wallet: buyer,
+            payment_account: ah.payment_account(&buyer),
+            transfer_authority: buyer,
+            treasury_mint: ah.treasury_mint,
+            token_account: listing.vault(),
+            token_mint: listing.token_mint,
+            metadata: listing.metadata(),
+            escrow_payment_account,
+            seller_payment_receipt_account: ah.payment_account(&listing.seller),
+            listing_proceeds_account: listing.listing_proceeds_account(),
+            buyer_receipt_token_account: get_associated_token_address(&buyer, &listing.token_mint),
+            buyer_refund_account: ah.payment_account(&buyer),
+            authority: ah.authority,
+            auctioneer_authority,
+            auction_house: ah.address,
+            auction_house_fee_account: ah.fee_account(),
+            auction_house_treasury: ah.treasury(),
+            buyer_trade_state,
+            seller_trade_state: listing.seller_trade_state(order_size).0,
+            free_trade_state,
+            ah_auctioneer_pda: ah.ah_auctioneer_pda(),
+            program_as_signer,
+            token_program: token::ID,
+            system_program: system_program::ID,
+            ata_program: associated_token::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::Buy {
+            trade_state_bump,
+            escrow_payment_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            auctioneer_authority_bump,
+            _token_size: listing.token_size,
+            order_size,
+            max_total_price,
+        },
+    );
+    ix.accounts.extend_from_slice(remaining_accounts);
+    ix
+}
+
+/// Cancels the listing and returns the unsold units to the seller's associated token account.
+pub fn auctioneer_cancel(listing: &ListingKeys) -> Instruction {
+    let ah = &listing.auction_house;
+    let (auctioneer_authority, auctioneer_authority_bump) = ah.auctioneer_authority();
+    build(
+        accounts::AuctioneerCancel {
+            auction_house_program: mpl_auction_house::ID,
+            listing_config: listing.listing_config().0,
+            listing_authority: listing.listing_authority().0,
+            wallet: listing.seller,
+            vault: listing.vault(),
+            token_account: get_associated_token_address(&listing.seller, &listing.token_mint),
+            token_mint: listing.token_mint,
+            listing_proceeds_account: listing.listing_proceeds_account(),
+            seller_payment_receipt_account: ah.payment_account(&listing.seller),
+            treasury_mint: ah.treasury_mint,
+            auctioneer_authority,
+            auction_house: ah.address,
+            ah_auctioneer_pda: ah.ah_auctioneer_pda(),
+            token_program: token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::AuctioneerCancel {
+            auctioneer_authority_bump,
+            token_size: listing.token_size,
+        },
+    )
+}
+
+/// Cancels the buyer's bid of `buyer_price` on `order_size` units.
+pub fn auctioneer_cancel_bid(
+    listing: &ListingKeys,
+    buyer: Pubkey,
+    buyer_price: u64,
+    order_size: u64,
+) -> Instruction {
+    let ah = &listing.auction_house;
+    let (auctioneer_authority, auctioneer_authority_bump) = ah.auctioneer_authority();
+    build(
+        accounts::AuctioneerCancelBid {
+            auction_house_program: mpl_auction_house::ID,
+            wallet: buyer,
+            token_account: listing.vault(),
+            token_mint: listing.token_mint,
+            authority: ah.authority,
+            auctioneer_authority,
+            auction_house: ah.address,
+            auction_house_fee_account: ah.fee_account(),
+            buyer_trade_state: listing.buyer_trade_state(&buyer, buyer_price, order_size).0,
+            ah_auctioneer_pda: ah.ah_auctioneer_pda(),
+            token_program: token::ID,
+        },
+        instruction::AuctioneerCancelBid {
+            auctioneer_authority_bump,
+            buyer_price,
+            token_size: order_size,
+        },
+    )
+}
+
+/// Withdraws `amount` from the wallet's escrow to its payment account.
+pub fn auctioneer_withdraw(ah: &AuctionHouseKeys, wallet: Pubkey, amount: u64) -> Instruction {
+    let (escrow_payment_account, escrow_payment_bump) = ah.escrow_payment_account(&wallet);
+    let (auctioneer_authority, auctioneer_authority_bump) = ah.auctioneer_authority();
+    build(
+        accounts::AuctioneerWithdraw {
+            auction_house_program: mpl_auction_house::ID,
+            wallet,
+            receipt_account: ah.payment_account(&wallet),
+            escrow_payment_account,
+            treasury_mint: ah.treasury_mint,
+            authority: ah.authority,
+            auctioneer_authority,
+            auction_house: ah.address,
+            auction_house_fee_account: ah.fee_account(),
+            ah_auctioneer_pda: ah.ah_auctioneer_pda(),
+            token_program: token::ID,
+            system_program: system_program::ID,
+            ata_program: associated_token::ID,
+            rent: sysvar::rent::ID,
+        },
+        instruction::AuctioneerWithdraw {
+            escrow_payment_bump,
+            auctioneer_authority_bump,
+            amount,
+        },
+    )
+}
+
+/// Prices `order_size` units; the `PriceQuote` comes back as the transaction's return data.
+pub fn quote(listing_config: Pubkey, order_size: u64) -> Instruction {
+    build(
+        accounts::Quote { listing_config },
+        instruction::Quote { order_size },
+    )
+}
+
+/// Upgrades `listing_config` to the current layout, with `payer` covering the rent of any
+/// space it grows by.
+pub fn migrate_listing_config(listing_config: Pubkey, payer: Pubkey) -> Instruction {
+    build(
+        accounts::MigrateListingConfig {
+            listing_config,
+            payer,
+            system_program: system_program::ID,
+        },
+        instruction::MigrateListingConfig {},
+    )
+}
+
+pub fn update_listing(listing: &ListingKeys, update: CurveUpdate) -> Instruction {
+    build(
+        accounts::UpdateListing {
+            auction_house_program: mpl_auction_house::ID,
+            listing_config: listing.listing_config().0,
+            wallet: listing.seller,
+            token_mint: listing.token_mint,
+            auction_house: listing.auction_house.address,
+        },
+        instruction::UpdateListing {
+            token_size: listing.token_size,
+            start_price: update.start_price,
+            reserve_price: update.reserve_price,
+            decay_constant: update.decay_constant,
+            scale_factor: update.scale_factor,
+            end_time: update.end_time,
+        },
+    )
+}
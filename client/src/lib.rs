@@ -0,0 +1,36 @@
+//! Off-chain helpers for the GDA auctioneer: PDA derivations, instruction builders and
+//! `ListingConfig` decoding. Seeds and layouts come from the program crate itself.
+
+use anchor_lang::prelude::*;
+use gda::errors::AuctioneerError;
+
+pub use gda::{quote::PriceQuote, ListingConfig, ListingMode, ID};
+
+pub mod pda;
+pub use pda::*;
+
+pub mod instructions;
+pub use instructions::*;
+
+/// Decodes a `ListingConfig` account, rejecting layouts newer than this crate knows.
+pub fn decode_listing_config(data: &[u8]) -> Result<ListingConfig> {
+    let listing_config = ListingConfig::try_deserialize(&mut &data[..])?;
+    if listing_config.version > ListingConfig::VERSION {
+        return err!(AuctioneerError::UnsupportedListingVersion);
+    }
+    Ok(listing_config)
+}
+
+/// Loads the account data at `address` with `load`, e.g. an RPC client's `get_account_data`,
+/// and decodes it as a `ListingConfig`.
+pub fn fetch_listing_config<F, E>(
+    load: F,
+    address: &Pubkey,
+) -> std::result::Result<ListingConfig, E>
+where
+    F: FnOnce(&Pubkey) -> std::result::Result<Vec<u8>, E>,
+    E: From<anchor_lang::error::Error>,
+{
+    let data = load(address)?;
+    Ok(decode_listing_config(&data)?)
+}
@@ -0,0 +1,160 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address;
+use gda::{AUCTIONEER_BUYER_PRICE, LISTING_AUTHORITY, LISTING_CONFIG};
+use mpl_auction_house::constants::AUCTIONEER;
+
+pub use mpl_auction_house::pda::{
+    find_auction_house_fee_account_address, find_auction_house_treasury_address,
+    find_auctioneer_pda, find_escrow_payment_address, find_program_as_signer_address,
+    find_trade_state_address,
+};
+pub use mpl_token_metadata::pda::find_metadata_account;
+
+/// The GDA program PDA that Auction House knows as the auctioneer authority.
+pub fn find_auctioneer_authority(auction_house: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[AUCTIONEER.as_bytes(), auction_house.as_ref()], &gda::ID)
+}
+
+pub fn find_listing_config(
+    seller: &Pubkey,
+    auction_house: &Pubkey,
+    treasury_mint: &Pubkey,
+    token_mint: &Pubkey,
+    token_size: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            LISTING_CONFIG.as_bytes(),
+            seller.as_ref(),
+            auction_house.as_ref(),
+            treasury_mint.as_ref(),
+            token_mint.as_ref(),
+            &token_size.to_le_bytes(),
+        ],
+        &gda::ID,
+    )
+}
+
+pub fn find_listing_authority(listing_config: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[LISTING_AUTHORITY.as_bytes(), listing_config.as_ref()],
+        &gda::ID,
+    )
+}
+
+/// The Auction House instance a listing is sold through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuctionHouseKeys {
+    pub address: Pubkey,
+    pub authority: Pubkey,
+    pub treasury_mint: Pubkey,
+}
+
+impl AuctionHouseKeys {
+    pub fn is_native(&self) -> bool {
+        self.treasury_mint == spl_token::native_mint::ID
+    }
+
+    pub fn fee_account(&self) -> Pubkey {
+        find_auction_house_fee_account_address(&self.address).0
+    }
+
+    pub fn treasury(&self) -> Pubkey {
+        find_auction_house_treasury_address(&self.address).0
+    }
+
+    pub fn auctioneer_authority(&self) -> (Pubkey, u8) {
+        find_auctioneer_authority(&self.address)
+    }
+
+    pub fn ah_auctioneer_pda(&self) -> Pubkey {
+        find_auctioneer_pda(&self.address, &self.auctioneer_authority().0).0
+    }
+
+    pub fn escrow_payment_account(&self, wallet: &Pubkey) -> (Pubkey, u8) {
+        find_escrow_payment_address(&self.address, wallet)
+    }
+
+    /// The wallet's treasury mint account, or the wallet itself when the treasury mint is native.
+    pub fn payment_account(&self, wallet: &Pubkey) -> Pubkey {
+        if self.is_native() {
+            *wallet
+        } else {
+            get_associated_token_address(wallet, &self.treasury_mint)
+        }
+    }
+}
+
+/// Everything that identifies a listing. The rest of its accounts are derived from these.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListingKeys {
+    pub auction_house: AuctionHouseKeys,
+    pub seller: Pubkey,
+    pub token_mint: Pubkey,
+    pub token_size: u64,
+}
+
+impl ListingKeys {
+    pub fn listing_config(&self) -> (Pubkey, u8) {
+        find_listing_config(
+            &self.seller,
+            &self.auction_house.address,
+            &self.auction_house.treasury_mint,
+            &self.token_mint,
+            self.token_size,
+        )
+    }
+
+    pub fn listing_authority(&self) -> (Pubkey, u8) {
+        find_listing_authority(&self.listing_config().0)
+    }
+
+    /// The listing authority's token account holding the unsold units.
+    pub fn vault(&self) -> Pubkey {
+        get_associated_token_address(&self.listing_authority().0, &self.token_mint)
+    }
+
+    /// Where Auction House pays a fill's proceeds before they are forwarded to the seller.
+    pub fn listing_proceeds_account(&self) -> Pubkey {
+        self.auction_house
+            .payment_account(&self.listing_authority().0)
+    }
+
+    pub fn metadata(&self) -> Pubkey {
+        find_metadata_account(&self.token_mint).0
+    }
+
+    /// A buyer's trade state for a bid of `price` on `order_size` units of this listing.
+    pub fn buyer_trade_state(&self, buyer: &Pubkey, price: u64, order_size: u64) -> (Pubkey, u8) {
+        find_trade_state_address(
+            buyer,
+            &self.auction_house.address,
+            &self.vault(),
+            &self.auction_house.treasury_mint,
+            &self.token_mint,
+            price,
+            order_size,
+        )
+    }
+
+    /// The trade state the listing authority sells a fill of `order_size` units through.
+    pub fn seller_trade_state(&self, order_size: u64) -> (Pubkey, u8) {
+        self.fill_trade_state(AUCTIONEER_BUYER_PRICE, order_size)
+    }
+
+    pub fn free_trade_state(&self, order_size: u64) -> (Pubkey, u8) {
+        self.fill_trade_state(0, order_size)
+    }
+
+    fn fill_trade_state(&self, price: u64, order_size: u64) -> (Pubkey, u8) {
+        find_trade_state_address(
+            &self.listing_authority().0,
+            &self.auction_house.address,
+            &self.vault(),
+            &self.auction_house.treasury_mint,
+            &self.token_mint,
+            price,
+            order_size,
+        )
+    }
+}
@@ -0,0 +1,348 @@
+use crate::{util::*, AuctionHouse, Auctioneer, *};
+use anchor_spl::associated_token::AssociatedToken;
+
+#[derive(Accounts)]
+#[instruction(
+    trade_state_bump: u8,
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    auctioneer_authority_bump: u8,
+    token_size: u64,
+    order_size: u64,
+    max_total_price: u64
+)]
+pub struct Buy<'info> {
+    /// Auction House Program
+    pub auction_house_program: Program<'info, AuctionHouseProgram>,
+
+    /// The Listing Config used for listing settings
+    #[account(
+        mut,
+        seeds=[
+            LISTING_CONFIG.as_bytes(),
+            seller.key().as_ref(),
+            auction_house.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_account.mint.as_ref(),
+            &token_size.to_le_bytes()
+        ],
+        bump = listing_config.bump,
+    )]
+    pub listing_config: Box<Account<'info, ListingConfig>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// PDA that owns the listing's vault and sells each fill on Auction House.
+    #[account(
+        mut,
+        seeds=[LISTING_AUTHORITY.as_bytes(), listing_config.key().as_ref()],
+        bump = listing_config.listing_authority_bump,
+    )]
+    pub listing_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Seller user wallet account.
+    #[account(mut)]
+    pub seller: UncheckedAccount<'info>,
+
+    /// Buyer wallet account.
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    /// CHECK: Verified through CPI
+    /// Buyer SOL or SPL account to transfer funds from.
+    #[account(mut)]
+    pub payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Verified through CPI
+    /// SPL token account transfer authority.
+    pub transfer_authority: UncheckedAccount<'info>,
+
+    /// Auction House instance treasury mint account.
+    pub treasury_mint: Box<Account<'info, Mint>>,
+
+    /// The listing's vault holding the unsold units.
+    #[account(mut, constraint = token_account.owner == listing_authority.key())]
+    pub token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Token mint account for the SPL token.
+    pub token_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Verified through CPI
+    /// Metaplex metadata account decorating SPL mint account.
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account PDA.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            wallet.key().as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump = escrow_payment_bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in VaultSale::settle.
+    /// Seller SOL or SPL account to receive payment at.
+    #[account(mut)]
+    pub seller_payment_receipt_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in VaultSale::settle.
+    /// Listing authority account Auction House pays the proceeds into before they are
+    /// forwarded to the seller: its treasury mint account, or the PDA itself when native.
+    #[account(mut)]
+    pub listing_proceeds_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in execute_sale_logic.
+    /// Buyer SPL token account to receive purchased item at.
+    #[account(mut)]
+    pub buyer_receipt_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in VaultSale::settle.
+    /// Buyer SOL or SPL account the difference between `max_total_price` and the curve price is
+    /// refunded to. For a native treasury mint this is the wallet itself.
+    #[account(mut)]
+    pub buyer_refund_account: UncheckedAccount<'info>,
+
+    /// CHECK: Verified with has_one constraint on auction house account.
+    /// Auction House instance authority.
+    pub authority: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// The auctioneer authority - typically a PDA of the Auctioneer program running this action.
+    #[account(seeds = [AUCTIONEER.as_bytes(), auction_house.key().as_ref()], bump = auctioneer_authority_bump)]
+    pub auctioneer_authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=treasury_mint,
+        has_one=auction_house_treasury,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        seeds::program=auction_house_program,
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance treasury account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            TREASURY.as_bytes()
+        ],
+        seeds::program=auction_house_program,
+        bump=auction_house.treasury_bump
+    )]
+    pub auction_house_treasury: UncheckedAccount<'info>,
+
+    /// CHECK: Verified through CPI
+    /// Buyer trade state PDA for a bid of `max_total_price`, created and closed again by this
+    /// instruction.
+    #[account(mut)]
+    pub buyer_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Seller trade state PDA account encoding the fill, created and closed by this instruction.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            listing_authority.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_account.mint.as_ref(),
+            &AUCTIONEER_BUYER_PRICE.to_le_bytes(),
+            &order_size.to_le_bytes()
+        ],
+        seeds::program=auction_house_program,
+        bump
+    )]
+    pub seller_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Free seller trade state PDA account encoding a free sell order.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            listing_authority.key().as_ref(),
+            auction_house.key().as_ref(),
+            token_account.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_account.mint.as_ref(),
+            &0u64.to_le_bytes(),
+            &order_size.to_le_bytes()
+        ],
+        seeds::program=auction_house_program,
+        bump = free_trade_state_bump
+    )]
+    pub free_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// The auctioneer PDA owned by Auction House storing scopes.
+    #[account(
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump = ah_auctioneer_pda.bump
+    )]
+    pub ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    #[account(
+        seeds=[
+            PREFIX.as_bytes(), SIGNER.as_bytes()
+        ],
+        seeds::program=auction_house_program,
+        bump = program_as_signer_bump
+    )]
+    pub program_as_signer: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Buys `order_size` units at the current GDA price in a single instruction. The buyer bids
+/// `max_total_price` through Auction House, so the bid does not depend on the price, and the
+/// sale is settled right away at the price computed on-chain, with the difference refunded.
+#[allow(clippy::too_many_arguments)]
+pub fn buy<'info>(
+    ctx: Context<'_, '_, '_, 'info, Buy<'info>>,
+    trade_state_bump: u8,
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    auctioneer_authority_bump: u8,
+    order_size: u64,
+    max_total_price: u64,
+) -> Result<()> {
+    let listing_config = &ctx.accounts.listing_config;
+    assert_auction_active(listing_config)?;
+    assert_valid_order_size(listing_config, order_size)?;
+    assert_covers_reserve(listing_config, order_size, max_total_price)?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let cumulative_price = listing_config.calculate_price_at(order_size, now)?;
+    if cumulative_price > max_total_price {
+        return err!(AuctioneerError::PriceExceedsLimit);
+    }
+
+    let accounts = &ctx.accounts;
+    auctioneer_place_order_logic(
+        accounts.auction_house_program.to_owned(),
+        accounts.wallet.to_owned(),
+        accounts.payment_account.to_owned(),
+        accounts.transfer_authority.to_owned(),
+        *accounts.treasury_mint.to_owned(),
+        *accounts.token_account.to_owned(),
+        accounts.metadata.to_owned(),
+        accounts.escrow_payment_account.to_owned(),
+        &accounts.auction_house,
+        accounts.auction_house_fee_account.to_owned(),
+        accounts.buyer_trade_state.to_owned(),
+        accounts.authority.to_owned(),
+        accounts.auctioneer_authority.to_owned(),
+        accounts.ah_auctioneer_pda.to_owned(),
+        accounts.token_program.to_owned(),
+        accounts.system_program.to_owned(),
+        accounts.rent.to_owned(),
+        trade_state_bump,
+        escrow_payment_bump,
+        auctioneer_authority_bump,
+        max_total_price,
+        order_size,
+    )?;
+
+    let seller_trade_state_bump = *ctx
+        .bumps
+        .get("seller_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let sale = VaultSale::new(
+        &accounts.listing_config,
+        order_size,
+        now,
+        accounts.auction_house_program.to_account_info(),
+        accounts.listing_authority.to_account_info(),
+        accounts.listing_proceeds_account.to_account_info(),
+        accounts.seller.to_account_info(),
+        accounts.seller_payment_receipt_account.to_account_info(),
+        accounts.wallet.to_account_info(),
+        accounts.buyer_refund_account.to_account_info(),
+        accounts.token_account.to_account_info(),
+        accounts.token_mint.to_account_info(),
+        accounts.metadata.to_account_info(),
+        accounts.treasury_mint.to_account_info(),
+        accounts.escrow_payment_account.to_account_info(),
+        accounts.buyer_receipt_token_account.to_account_info(),
+        accounts.authority.to_account_info(),
+        accounts.auctioneer_authority.to_account_info(),
+        accounts.auction_house.to_account_info(),
+        accounts.auction_house_fee_account.to_account_info(),
+        accounts.auction_house_treasury.to_account_info(),
+        accounts.buyer_trade_state.to_account_info(),
+        accounts.seller_trade_state.to_account_info(),
+        accounts.free_trade_state.to_account_info(),
+        accounts.ah_auctioneer_pda.to_account_info(),
+        accounts.program_as_signer.to_account_info(),
+        accounts.token_program.to_account_info(),
+        accounts.system_program.to_account_info(),
+        accounts.ata_program.to_account_info(),
+        accounts.rent.to_account_info(),
+    )?;
+    let settled = sale.settle(
+        ctx.remaining_accounts,
+        escrow_payment_bump,
+        free_trade_state_bump,
+        program_as_signer_bump,
+        auctioneer_authority_bump,
+        seller_trade_state_bump,
+        max_total_price,
+        cumulative_price,
+        order_size,
+    )?;
+
+    let buyer = ctx.accounts.wallet.key();
+    let seller = ctx.accounts.seller.key();
+    record_fill(
+        &mut ctx.accounts.listing_config,
+        buyer,
+        seller,
+        order_size,
+        cumulative_price,
+        settled,
+        now,
+    )
+}
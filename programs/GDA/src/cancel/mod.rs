@@ -0,0 +1,382 @@
+use crate::{util::*, AuctionHouse, Auctioneer, *};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address;
+
+#[derive(Accounts)]
+#[instruction(auctioneer_authority_bump: u8, token_size: u64)]
+pub struct AuctioneerCancel<'info> {
+    /// Auction House Program
+    pub auction_house_program: Program<'info, AuctionHouseProgram>,
+
+    /// The Listing Config being withdrawn. Closed to the seller once the listing is cancelled.
+    #[account(
+        mut,
+        close = wallet,
+        seeds=[
+            LISTING_CONFIG.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint.key().as_ref(),
+            &token_size.to_le_bytes()
+        ],
+        bump = listing_config.bump,
+    )]
+    pub listing_config: Account<'info, ListingConfig>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// PDA that owns the listing's vault.
+    #[account(
+        mut,
+        seeds=[LISTING_AUTHORITY.as_bytes(), listing_config.key().as_ref()],
+        bump = listing_config.listing_authority_bump,
+    )]
+    pub listing_authority: UncheckedAccount<'info>,
+
+    /// Seller wallet account.
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    /// The listing's vault. The unsold units are returned from it and it is closed.
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = listing_authority,
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+
+    /// Seller token account the unsold units are returned to.
+    #[account(mut, constraint = token_account.mint == token_mint.key())]
+    pub token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Token mint account of the SPL token.
+    pub token_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: Validated in auctioneer_cancel.
+    /// Listing authority's treasury mint account, closed for SPL auction houses. Same as the
+    /// listing authority for native ones.
+    #[account(mut)]
+    pub listing_proceeds_account: UncheckedAccount<'info>,
+
+    /// CHECK: Validated in auctioneer_cancel.
+    /// Seller treasury mint account that any balance left in the proceeds account is swept to.
+    /// Same as the wallet for native auction houses.
+    #[account(mut)]
+    pub seller_payment_receipt_account: UncheckedAccount<'info>,
+
+    /// Auction House instance treasury mint account.
+    pub treasury_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// The auctioneer authority - typically a PDA of the Auctioneer program running this action.
+    #[account(seeds = [AUCTIONEER.as_bytes(), auction_house.key().as_ref()], bump = auctioneer_authority_bump)]
+    pub auctioneer_authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump=auction_house.bump,
+        has_one=treasury_mint
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// The auctioneer PDA owned by Auction House storing scopes.
+    #[account(
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump = ah_auctioneer_pda.bump
+    )]
+    pub ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Withdraws a listing: the unsold units in the vault go back to the seller, the vault and the
+/// listing authority's proceeds account are closed, with any balance left in the latter swept
+/// to the seller, and the `ListingConfig` is closed with its rent returned to the seller.
+///
+/// Every fill lists, settles and closes its own seller trade state within one instruction, so
+/// nothing is left open on Auction House and units already sold stay with their buyers.
+/// Bids still resting against the listing are not touched; their buyers reclaim the escrow
+/// with `auctioneer_cancel_bid` and `auctioneer_withdraw`.
+pub fn auctioneer_cancel<'info>(
+    ctx: Context<'_, '_, '_, 'info, AuctioneerCancel<'info>>,
+    _auctioneer_authority_bump: u8,
+    _token_size: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+    let auctioneer_authority = &ctx.accounts.auctioneer_authority;
+    let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
+
+    if !auction_house.has_auctioneer {
+        return Err(AuctionHouseError::NoAuctioneerProgramSet.into());
+    }
+
+    assert_valid_auctioneer(
+        auction_house,
+        &auctioneer_authority.key(),
+        ah_auctioneer_pda,
+    )?;
+
+    let accounts = &ctx.accounts;
+    let listing_config_key = accounts.listing_config.key();
+    let listing_authority_seeds = [
+        LISTING_AUTHORITY.as_bytes(),
+        listing_config_key.as_ref(),
+        &[accounts.listing_config.listing_authority_bump],
+    ];
+    let listing_authority_signer = [&listing_authority_seeds[..]];
+    let token_program = accounts.token_program.to_account_info();
+    let listing_authority = accounts.listing_authority.to_account_info();
+    let units_returned = accounts.vault.amount;
+
+    if units_returned > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                token::Transfer {
+                    from: accounts.vault.to_account_info(),
+                    to: accounts.token_account.to_account_info(),
+                    authority: listing_authority.clone(),
+                },
+                &listing_authority_signer,
+            ),
+            units_returned,
+        )?;
+    }
+    token::close_account(CpiContext::new_with_signer(
+        token_program.clone(),
+        token::CloseAccount {
+            account: accounts.vault.to_account_info(),
+            destination: accounts.wallet.to_account_info(),
+            authority: listing_authority.clone(),
+        },
+        &listing_authority_signer,
+    ))?;
+
+    // Proceeds are forwarded to the seller on every fill, but anyone can still send tokens to
+    // the account, and a non-zero balance would make the close fail.
+    if accounts.treasury_mint.key() != spl_token::native_mint::id() {
+        assert_keys_equal(
+            accounts.listing_proceeds_account.key(),
+            get_associated_token_address(&listing_authority.key(), &accounts.treasury_mint.key()),
+        )?;
+        let stray_proceeds =
+            Account::<TokenAccount>::try_from(&accounts.listing_proceeds_account)?.amount;
+        if stray_proceeds > 0 {
+            let receipt = Account::<TokenAccount>::try_from(&accounts.seller_payment_receipt_account)?;
+            if receipt.owner != accounts.wallet.key() || receipt.mint != accounts.treasury_mint.key() {
+                return err!(AuctioneerError::InvalidSellerReceipt);
+            }
+            token::transfer(
+                CpiContext::new_with_signer(
+                    token_program.clone(),
+                    token::Transfer {
+                        from: accounts.listing_proceeds_account.to_account_info(),
+                        to: accounts.seller_payment_receipt_account.to_account_info(),
+                        authority: listing_authority.clone(),
+                    },
+                    &listing_authority_signer,
+                ),
+                stray_proceeds,
+            )?;
+        }
+        token::close_account(CpiContext::new_with_signer(
+            token_program,
+            token::CloseAccount {
+                account: accounts.listing_proceeds_account.to_account_info(),
+                destination: accounts.wallet.to_account_info(),
+                authority: listing_authority.clone(),
+            },
+            &listing_authority_signer,
+        ))?;
+    }
+
+    let stray_lamports = listing_authority.lamports();
+    if stray_lamports > 0 {
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: listing_authority,
+                    to: accounts.wallet.to_account_info(),
+                },
+                &listing_authority_signer,
+            ),
+            stray_lamports,
+        )?;
+    }
+
+    emit!(ListingCancelled {
+        listing_config: listing_config_key,
+        seller: accounts.wallet.key(),
+        items_sold: accounts.listing_config.items_sold,
+        units_returned,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(auctioneer_authority_bump: u8, buyer_price: u64, token_size: u64)]
+pub struct AuctioneerCancelBid<'info> {
+    /// Auction House Program
+    pub auction_house_program: Program<'info, AuctionHouseProgram>,
+
+    /// Buyer wallet account. Signs so that the trade state rent is refunded to it.
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+
+    /// SPL token account the bid was placed on.
+    pub token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Token mint account of the SPL token.
+    pub token_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: Verified through CPI
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// The auctioneer authority - typically a PDA of the Auctioneer program running this action.
+    #[account(seeds = [AUCTIONEER.as_bytes(), auction_house.key().as_ref()], bump = auctioneer_authority_bump)]
+    pub auctioneer_authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump=auction_house.bump,
+        has_one=authority,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        seeds::program=auction_house_program,
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Verified through CPI
+    /// Buyer trade state PDA account created by `place_order`.
+    #[account(mut)]
+    pub buyer_trade_state: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// The auctioneer PDA owned by Auction House storing scopes.
+    #[account(
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump = ah_auctioneer_pda.bump
+    )]
+    pub ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Cancels a bid placed through `place_order`. Auction House zeroes the buyer trade state and
+/// refunds its rent to the buyer; the escrowed funds stay put until `auctioneer_withdraw`.
+pub fn auctioneer_cancel_bid<'info>(
+    ctx: Context<'_, '_, '_, 'info, AuctioneerCancelBid<'info>>,
+    auctioneer_authority_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+    let auctioneer_authority = &ctx.accounts.auctioneer_authority;
+    let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
+
+    if !auction_house.has_auctioneer {
+        return Err(AuctionHouseError::NoAuctioneerProgramSet.into());
+    }
+
+    assert_valid_auctioneer(
+        auction_house,
+        &auctioneer_authority.key(),
+        ah_auctioneer_pda,
+    )?;
+
+    let accounts = &ctx.accounts;
+    let cpi_accounts = AHCancel {
+        wallet: accounts.wallet.to_account_info(),
+        token_account: accounts.token_account.to_account_info(),
+        token_mint: accounts.token_mint.to_account_info(),
+        authority: accounts.authority.to_account_info(),
+        auctioneer_authority: accounts.auctioneer_authority.to_account_info(),
+        auction_house: accounts.auction_house.to_account_info(),
+        auction_house_fee_account: accounts.auction_house_fee_account.to_account_info(),
+        trade_state: accounts.buyer_trade_state.to_account_info(),
+        ah_auctioneer_pda: accounts.ah_auctioneer_pda.to_account_info(),
+        token_program: accounts.token_program.to_account_info(),
+    };
+
+    auctioneer_cancel_logic(
+        accounts.auction_house_program.to_account_info(),
+        cpi_accounts,
+        auctioneer_authority_bump,
+        buyer_price,
+        token_size,
+    )
+}
+
+/// Invokes Auction House's `auctioneer_cancel` on the buyer trade state in `cpi_accounts`.
+#[inline(never)]
+pub(crate) fn auctioneer_cancel_logic<'info>(
+    cpi_program: AccountInfo<'info>,
+    cpi_accounts: AHCancel<'info>,
+    auctioneer_authority_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+) -> Result<()> {
+    let cancel_data = mpl_auction_house::instruction::AuctioneerCancel {
+        buyer_price,
+        token_size,
+    };
+
+    let ah_key = cpi_accounts.auction_house.key();
+    let auctioneer_seeds = [
+        AUCTIONEER.as_bytes(),
+        ah_key.as_ref(),
+        &[auctioneer_authority_bump],
+    ];
+
+    invoke_auction_house(
+        &cpi_program,
+        &cpi_accounts,
+        &[],
+        cancel_data.data(),
+        &[&auctioneer_seeds],
+    )?;
+
+    Ok(())
+}
+
@@ -31,7 +31,7 @@ pub enum AuctioneerError {
     NotHighestBidder,
 
     // 6007
-    #[msg("The bid price must be greater than the reserve price")]
+    #[msg("The bid does not cover the reserve price of every unit ordered")]
     BelowReservePrice,
 
     // 6008
@@ -41,4 +41,96 @@ pub enum AuctioneerError {
     // 6009
     #[msg("The highest bidder is not allowed to cancel")]
     CannotCancelHighestBid,
-}
\ No newline at end of file
+
+    // 6010
+    #[msg("All items in this listing have been sold")]
+    SoldOut,
+
+    // 6011
+    #[msg("Continuous listings need a non-zero emission rate")]
+    InvalidEmissionRate,
+
+    // 6012
+    #[msg("Not enough units have been emitted to fill this order")]
+    InsufficientEmission,
+
+    // 6013
+    #[msg("Decay constant must be between 1 and 36,000,000 basis points per hour")]
+    InvalidDecayConstant,
+
+    // 6014
+    #[msg("The budget does not cover a single unit at the current price")]
+    BudgetTooLow,
+
+    // 6015
+    #[msg("The cumulative price is above the buyer's maximum")]
+    PriceExceedsLimit,
+
+    // 6016
+    #[msg("The auction must start before it ends")]
+    InvalidAuctionWindow,
+
+    // 6017
+    #[msg("The bid is below the current GDA price")]
+    BidBelowCurvePrice,
+
+    // 6018
+    #[msg("The seller payment receipt account must be the seller's treasury mint account")]
+    InvalidSellerReceipt,
+
+    // 6019
+    #[msg("A listing must offer at least one unit")]
+    InvalidTokenSize,
+
+    // 6020
+    #[msg("The token account holds fewer units than the listing offers")]
+    InsufficientTokenBalance,
+
+    // 6021
+    #[msg("The auction end time is in the past")]
+    EndTimeInPast,
+
+    // 6022
+    #[msg("The start price must be greater than zero")]
+    InvalidStartPrice,
+
+    // 6023
+    #[msg("Discrete listings need a scale factor above 10,000 basis points")]
+    InvalidScaleFactor,
+
+    // 6024
+    #[msg("The listing config was written by a newer version of the program")]
+    UnsupportedListingVersion,
+
+    // 6025
+    #[msg("Only the end time can change once units have been sold")]
+    CurveLocked,
+
+    // 6026
+    #[msg("The end time can only be extended once units have been sold")]
+    CannotShortenAuction,
+
+    // 6027
+    #[msg("The listing authority's token account has the wrong address, owner or mint")]
+    InvalidListingTokenAccount,
+
+    // 6028
+    #[msg("The reserve price must be below the start price")]
+    InvalidReservePrice,
+
+    // 6029
+    #[msg("The buyer trade state was derived for a different order size than the budget buys")]
+    OrderSizeMismatch,
+
+    // 6030
+    #[msg("The order size must be between one and the units left in the listing")]
+    InvalidOrderSize,
+
+    // 6031
+    #[msg("The buyer refund account must be the buyer wallet or the buyer's treasury mint account")]
+    InvalidBuyerRefundAccount,
+
+    // 6032
+    #[msg("The seller's proceeds do not cover the refund of the bid above the curve price")]
+    RefundExceedsProceeds,
+}
@@ -0,0 +1,72 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::clock::UnixTimestamp;
+
+use crate::ListingMode;
+
+/// A listing was created by `auctioneer_sell`.
+#[event]
+pub struct ListingCreated {
+    pub listing_config: Pubkey,
+    pub seller: Pubkey,
+    pub token_mint: Pubkey,
+    pub treasury_mint: Pubkey,
+    pub token_size: u64,
+    pub start_price: u64,
+    pub reserve_price: u64,
+    pub decay_const: u64,
+    pub scale_factor: u64,
+    pub mode: ListingMode,
+    pub emission_rate: u64,
+    pub start_timestamp: UnixTimestamp,
+    pub end_timestamp: UnixTimestamp,
+    pub timestamp: UnixTimestamp,
+}
+
+/// A bid was placed through `place_order` or `place_order_with_budget`.
+#[event]
+pub struct OrderPlaced {
+    pub listing_config: Pubkey,
+    pub buyer: Pubkey,
+    pub order_size: u64,
+    /// Cumulative price of the order when the bid was placed.
+    pub cumulative_price: u64,
+    /// Amount bid and escrowed, the most the order can settle at.
+    pub buyer_price: u64,
+    pub timestamp: UnixTimestamp,
+}
+
+/// A fill was settled by `auctioneer_execute_sale` or `buy`.
+#[event]
+pub struct SaleExecuted {
+    pub listing_config: Pubkey,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub order_size: u64,
+    /// Price paid by the buyer, before fees and royalties.
+    pub price: u64,
+    /// Units sold on the listing including this fill.
+    pub items_sold: u64,
+    pub marketplace_fee: u64,
+    pub royalties: u64,
+    pub seller_proceeds: u64,
+    pub timestamp: UnixTimestamp,
+}
+
+/// A listing was withdrawn by `auctioneer_cancel`.
+#[event]
+pub struct ListingCancelled {
+    pub listing_config: Pubkey,
+    pub seller: Pubkey,
+    pub items_sold: u64,
+    /// Unsold units returned to the seller.
+    pub units_returned: u64,
+    pub timestamp: UnixTimestamp,
+}
+
+/// The last units of a listing were sold.
+#[event]
+pub struct AuctionSoldOut {
+    pub listing_config: Pubkey,
+    pub items_sold: u64,
+    pub timestamp: UnixTimestamp,
+}
@@ -1,11 +1,6 @@
-use crate::{ errors::*, util::*, AuctionHouse, Auctioneer, *};
-use anchor_lang::{
-   // prelude::*,
-    solana_program::{program::invoke,},
-    AnchorDeserialize,
-};
-use solana_program::program_memory::sol_memset;
-use spl_token::{ instruction::revoke};
+use crate::{cancel::auctioneer_cancel_logic, util::*, AuctionHouse, Auctioneer, *};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
 
 pub const TREASURY: &str = "treasury";
 #[derive(Accounts)]
@@ -13,14 +8,18 @@ pub const TREASURY: &str = "treasury";
     escrow_payment_bump: u8,
     free_trade_state_bump: u8,
     program_as_signer_bump: u8,
+    auctioneer_authority_bump: u8,
     buyer_price: u64,
-    token_size: u64
+    token_size: u64,
+    order_size: u64
 )]
 pub struct AuctioneerExecuteSale<'info> {
-    /// CHECK: Validated in execute_sale_logic.
-    /// Buyer user wallet account.
+    /// Auction House Program
+    pub auction_house_program: Program<'info, AuctionHouseProgram>,
+
+    /// Buyer user wallet account. Fronts the rent of the seller trade state for the fill.
     #[account(mut)]
-    pub buyer: UncheckedAccount<'info>,
+    pub buyer: Signer<'info>,
 
     #[account(
         mut,
@@ -28,7 +27,6 @@ pub struct AuctioneerExecuteSale<'info> {
             LISTING_CONFIG.as_bytes(),
             seller.key().as_ref(),
             auction_house.key().as_ref(),
-            token_account.key().as_ref(),
             auction_house.treasury_mint.as_ref(),
             token_account.mint.as_ref(),
             &token_size.to_le_bytes()
@@ -36,15 +34,23 @@ pub struct AuctioneerExecuteSale<'info> {
         bump = listing_config.bump,
     )]
     pub listing_config: Account<'info, ListingConfig>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// PDA that owns the listing's vault and sells each fill on Auction House.
+    #[account(
+        mut,
+        seeds=[LISTING_AUTHORITY.as_bytes(), listing_config.key().as_ref()],
+        bump = listing_config.listing_authority_bump,
+    )]
+    pub listing_authority: UncheckedAccount<'info>,
+
     /// CHECK: Validated in execute_sale_logic.
     /// Seller user wallet account.
     #[account(mut)]
     pub seller: UncheckedAccount<'info>,
 
-    /// CHECK: Validated in execute_sale_logic.
-    // cannot mark these as real Accounts or else we blow stack size limit
-    ///Token account where the SPL token is stored.
-    #[account(mut)]
+    /// The listing's vault holding the unsold units.
+    #[account(mut, constraint = token_account.owner == listing_authority.key())]
     pub token_account: Box<Account<'info, TokenAccount>>,
 
     /// CHECK: Validated in execute_sale_logic.
@@ -69,27 +75,41 @@ pub struct AuctioneerExecuteSale<'info> {
             auction_house.key().as_ref(),
             buyer.key().as_ref()
         ],
+        seeds::program=auction_house_program,
         bump
     )]
     pub escrow_payment_account: UncheckedAccount<'info>,
 
-    /// CHECK: Validated in execute_sale_logic.
+    /// CHECK: Validated in VaultSale::settle.
     /// Seller SOL or SPL account to receive payment at.
     #[account(mut)]
     pub seller_payment_receipt_account: UncheckedAccount<'info>,
 
+    /// CHECK: Validated in VaultSale::settle.
+    /// Listing authority account Auction House pays the proceeds into before they are
+    /// forwarded to the seller: its treasury mint account, or the PDA itself when native.
+    #[account(mut)]
+    pub listing_proceeds_account: UncheckedAccount<'info>,
+
     /// CHECK: Validated in execute_sale_logic.
     /// Buyer SPL token account to receive purchased item at.
     #[account(mut)]
     pub buyer_receipt_token_account: UncheckedAccount<'info>,
 
+    /// CHECK: Validated in VaultSale::settle.
+    /// Buyer SOL or SPL account the difference between the bid and the curve price is refunded
+    /// to. For a native treasury mint this is the buyer wallet itself.
+    #[account(mut)]
+    pub buyer_refund_account: UncheckedAccount<'info>,
+
     /// CHECK: Validated in execute_sale_logic.
     /// Auction House instance authority.
     pub authority: UncheckedAccount<'info>,
 
-    /// CHECK: Validated in ah_auctioneer_pda seeds and execute_sale_logic.
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
     /// The auctioneer authority - typically a PDA of the Auctioneer program running this action.
-    pub auctioneer_authority: Signer<'info>,
+    #[account(seeds = [AUCTIONEER.as_bytes(), auction_house.key().as_ref()], bump = auctioneer_authority_bump)]
+    pub auctioneer_authority: UncheckedAccount<'info>,
 
     /// Auction House instance PDA account.
     #[account(
@@ -98,6 +118,7 @@ pub struct AuctioneerExecuteSale<'info> {
             auction_house.creator.as_ref(),
             auction_house.treasury_mint.as_ref()
         ],
+        seeds::program=auction_house_program,
         bump=auction_house.bump,
         has_one=authority,
         has_one=treasury_mint,
@@ -115,6 +136,7 @@ pub struct AuctioneerExecuteSale<'info> {
             auction_house.key().as_ref(),
             FEE_PAYER.as_bytes()
         ],
+        seeds::program=auction_house_program,
         bump=auction_house.fee_payer_bump
     )]
     pub auction_house_fee_account: UncheckedAccount<'info>,
@@ -128,6 +150,7 @@ pub struct AuctioneerExecuteSale<'info> {
             auction_house.key().as_ref(),
             TREASURY.as_bytes()
         ],
+        seeds::program=auction_house_program,
         bump=auction_house.treasury_bump
     )]
     pub auction_house_treasury: UncheckedAccount<'info>,
@@ -138,20 +161,21 @@ pub struct AuctioneerExecuteSale<'info> {
     pub buyer_trade_state: UncheckedAccount<'info>,
 
     /// CHECK: Not dangerous. Account seeds checked in constraint.
-    /// Seller trade state PDA account encoding the sell order.
+    /// Seller trade state PDA account encoding the fill, created and closed by this instruction.
     #[account(
         mut,
         seeds = [
             PREFIX.as_bytes(),
-            seller.key().as_ref(),
+            listing_authority.key().as_ref(),
             auction_house.key().as_ref(),
             token_account.key().as_ref(),
             auction_house.treasury_mint.as_ref(),
             token_mint.key().as_ref(),
-            &u64::MAX.to_le_bytes(),
-            &token_size.to_le_bytes()
+            &AUCTIONEER_BUYER_PRICE.to_le_bytes(),
+            &order_size.to_le_bytes()
         ],
-        bump=seller_trade_state.to_account_info().data.borrow()[0]
+        seeds::program=auction_house_program,
+        bump
     )]
     pub seller_trade_state: UncheckedAccount<'info>,
 
@@ -161,14 +185,15 @@ pub struct AuctioneerExecuteSale<'info> {
         mut,
         seeds = [
             PREFIX.as_bytes(),
-            seller.key().as_ref(),
+            listing_authority.key().as_ref(),
             auction_house.key().as_ref(),
             token_account.key().as_ref(),
             auction_house.treasury_mint.as_ref(),
             token_mint.key().as_ref(),
             &0u64.to_le_bytes(),
-            &token_size.to_le_bytes()
+            &order_size.to_le_bytes()
         ],
+        seeds::program=auction_house_program,
         bump
     )]
     pub free_trade_state: UncheckedAccount<'info>,
@@ -181,6 +206,7 @@ pub struct AuctioneerExecuteSale<'info> {
             auction_house.key().as_ref(),
             auctioneer_authority.key().as_ref()
         ],
+        seeds::program=auction_house_program,
         bump = ah_auctioneer_pda.bump
     )]
     pub ah_auctioneer_pda: Account<'info, Auctioneer>,
@@ -194,6 +220,7 @@ pub struct AuctioneerExecuteSale<'info> {
         seeds=[
             PREFIX.as_bytes(), SIGNER.as_bytes()
         ],
+        seeds::program=auction_house_program,
         bump
     )]
     pub program_as_signer: UncheckedAccount<'info>,
@@ -202,16 +229,22 @@ pub struct AuctioneerExecuteSale<'info> {
 }
 
 
-
-
+/// Settles a bid placed through `place_order` for `order_size` of the listing's `token_size`
+/// units, while the auction is running. The cumulative price is recomputed from the listing's
+/// state at settlement: bids below it are rejected and bids above it pay the curve price, with
+/// the difference refunded to the buyer out of the proceeds in lamports or treasury mint tokens.
+#[allow(clippy::too_many_arguments)]
 pub fn auctioneer_execute_sale<'info>(
     ctx: Context<'_, '_, '_, 'info, AuctioneerExecuteSale<'info>>,
     escrow_payment_bump: u8,
     free_trade_state_bump: u8,
     program_as_signer_bump: u8,
+    auctioneer_authority_bump: u8,
     buyer_price: u64,
+    _token_size: u64,
     order_size: u64,
 ) -> Result<()> {
+    assert_auction_active(&ctx.accounts.listing_config)?;
     let auction_house = &ctx.accounts.auction_house;
     let auctioneer_authority = &ctx.accounts.auctioneer_authority;
     let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
@@ -238,6 +271,10 @@ pub fn auctioneer_execute_sale<'info>(
         .bumps
         .get("program_as_signer")
         .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
+    let seller_trade_state_bump = *ctx
+        .bumps
+        .get("seller_trade_state")
+        .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?;
 
     if (escrow_canonical_bump != escrow_payment_bump)
         || (free_trade_state_canonical_bump != free_trade_state_bump)
@@ -246,396 +283,549 @@ pub fn auctioneer_execute_sale<'info>(
         return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
     }
 
-    // Duplicate the logic methods to avoid going over the compute limit.
-    auctioneer_execute_sale_logic(
-        ctx.accounts,
+    let now = Clock::get()?.unix_timestamp;
+    // The curve price already clamps every unit to the reserve price.
+    let price = ctx.accounts.listing_config.calculate_price_at(order_size, now)?;
+    let accounts = &ctx.accounts;
+    let sale = VaultSale::new(
+        &accounts.listing_config,
+        order_size,
+        now,
+        accounts.auction_house_program.to_account_info(),
+        accounts.listing_authority.to_account_info(),
+        accounts.listing_proceeds_account.to_account_info(),
+        accounts.seller.to_account_info(),
+        accounts.seller_payment_receipt_account.to_account_info(),
+        accounts.buyer.to_account_info(),
+        accounts.buyer_refund_account.to_account_info(),
+        accounts.token_account.to_account_info(),
+        accounts.token_mint.to_account_info(),
+        accounts.metadata.to_account_info(),
+        accounts.treasury_mint.to_account_info(),
+        accounts.escrow_payment_account.to_account_info(),
+        accounts.buyer_receipt_token_account.to_account_info(),
+        accounts.authority.to_account_info(),
+        accounts.auctioneer_authority.to_account_info(),
+        accounts.auction_house.to_account_info(),
+        accounts.auction_house_fee_account.to_account_info(),
+        accounts.auction_house_treasury.to_account_info(),
+        accounts.buyer_trade_state.to_account_info(),
+        accounts.seller_trade_state.to_account_info(),
+        accounts.free_trade_state.to_account_info(),
+        accounts.ah_auctioneer_pda.to_account_info(),
+        accounts.program_as_signer.to_account_info(),
+        accounts.token_program.to_account_info(),
+        accounts.system_program.to_account_info(),
+        accounts.ata_program.to_account_info(),
+        accounts.rent.to_account_info(),
+    )?;
+    let settled = sale.settle(
         ctx.remaining_accounts,
         escrow_payment_bump,
         free_trade_state_bump,
         program_as_signer_bump,
+        auctioneer_authority_bump,
+        seller_trade_state_bump,
         buyer_price,
+        price,
         order_size,
     )?;
-    let listing = &mut ctx.accounts.listing_config;
-    listing.items_sold =  listing.items_sold.checked_add(order_size).unwrap();
-    if listing.items_sold == listing.token_size{
-        return Err(AuctioneerError::SoldOut.into());
-    }
-    Ok(())
-}
-
 
-
-
-
-#[inline(never)]
-fn auctioneer_execute_sale_logic<'c, 'info>(
-    accounts: &mut AuctioneerExecuteSale<'info>,
-    remaining_accounts: &'c [AccountInfo<'info>],
-    escrow_payment_bump: u8,
-    _free_trade_state_bump: u8,
-    program_as_signer_bump: u8,
-    buyer_price: u64,
-    order_size: u64,
-) -> Result<()> {
-    let buyer = &accounts.buyer;
-    let seller = &accounts.seller;
-    let token_account = &accounts.token_account;
-    let token_mint = &accounts.token_mint;
-    let metadata = &accounts.metadata;
-    let treasury_mint = &accounts.treasury_mint;
-    let seller_payment_receipt_account = &accounts.seller_payment_receipt_account;
-    let buyer_receipt_token_account = &accounts.buyer_receipt_token_account;
-    let escrow_payment_account = &accounts.escrow_payment_account;
-    let authority = &accounts.authority;
-    let auction_house = &accounts.auction_house;
-    let auction_house_fee_account = &accounts.auction_house_fee_account;
-    let auction_house_treasury = &accounts.auction_house_treasury;
-    let buyer_trade_state = &accounts.buyer_trade_state;
-    let seller_trade_state = &accounts.seller_trade_state;
-    let free_trade_state = &accounts.free_trade_state;
-    let token_program = &accounts.token_program;
-    let system_program = &accounts.system_program;
-    let ata_program = &accounts.ata_program;
-    let program_as_signer = &accounts.program_as_signer;
-    let rent = &accounts.rent;
-
-    let metadata_clone = metadata.to_account_info();
-    let escrow_clone = escrow_payment_account.to_account_info();
-    let auction_house_clone = auction_house.to_account_info();
-    let ata_clone = ata_program.to_account_info();
-    let token_clone = token_program.to_account_info();
-    let sys_clone = system_program.to_account_info();
-    let rent_clone = rent.to_account_info();
-    let treasury_clone = auction_house_treasury.to_account_info();
-    let authority_clone = authority.to_account_info();
-    let buyer_receipt_clone = buyer_receipt_token_account.to_account_info();
-    let token_account_clone = token_account.to_account_info();
-
-    let is_native = treasury_mint.key() == spl_token::native_mint::id();
-
-    if buyer_price == 0 && !authority_clone.is_signer && !seller.is_signer {
-        return Err(
-            AuctionHouseError::CannotMatchFreeSalesWithoutAuctionHouseOrSellerSignoff.into(),
-        );
-    }
-
-    let token_account_mint = get_mint_from_token_account(&token_account_clone)?;
-
-    assert_keys_equal(token_mint.key(), token_account_mint)?;
-    let delegate = get_delegate_from_token_account(&token_account_clone)?;
-    if let Some(d) = delegate {
-        assert_keys_equal(program_as_signer.key(), d)?;
-    } else {
-        msg!("No delegate detected on token account.");
-        return Err(AuctionHouseError::BothPartiesNeedToAgreeToSale.into());
-    }
-    let buyer_ts_data = &mut buyer_trade_state.try_borrow_mut_data()?;
-    let seller_ts_data = &mut seller_trade_state.try_borrow_mut_data()?;
-
-    let ts_bump = if buyer_ts_data.len() > 0 {
-        buyer_ts_data[0]
-    } else {
-        return Err(AuctionHouseError::BuyerTradeStateNotValid.into());
-    };
-
-    if ts_bump == 0 || seller_ts_data.len() == 0 || seller_ts_data[0] == 0 {
-        return Err(AuctionHouseError::BothPartiesNeedToAgreeToSale.into());
-    }
-
-    let token_account_data = token_account;
-    if token_account_data.amount < order_size{
-        return Err(AuctionHouseError::NotEnoughTokensAvailableForPurchase.into());
-    }
-    assert_valid_trade_state(
-        &buyer.key(),
-        auction_house,
-        buyer_price,
+    let buyer = ctx.accounts.buyer.key();
+    let seller = ctx.accounts.seller.key();
+    record_fill(
+        &mut ctx.accounts.listing_config,
+        buyer,
+        seller,
         order_size,
-        buyer_trade_state,
-        &token_mint.key(),
-        &token_account.key(),
-        ts_bump,
-    )?;
-   
-
-    let auction_house_key = auction_house.key();
-    let seeds = [
-        PREFIX.as_bytes(),
-        auction_house_key.as_ref(),
-        FEE_PAYER.as_bytes(),
-        &[auction_house.fee_payer_bump],
-    ];
-
-    let wallet_to_use = if buyer.is_signer { buyer } else { seller };
-
-    let (fee_payer, fee_payer_seeds) = get_fee_payer(
-        authority,
-        auction_house,
-        wallet_to_use.to_account_info(),
-        auction_house_fee_account.to_account_info(),
-        &seeds,
-    )?;
-    let fee_payer_clone = fee_payer.to_account_info();
+        price,
+        settled,
+        now,
+    )
+}
 
-    assert_is_ata(
-        &token_account.to_account_info(),
-        &seller.key(),
-        &token_account_mint,
-    )?;
-    assert_derivation(
-        &mpl_token_metadata::id(),
-        &metadata.to_account_info(),
-        &[
-            mpl_token_metadata::state::PREFIX.as_bytes(),
-            mpl_token_metadata::id().as_ref(),
-            token_account_mint.as_ref(),
-        ],
-    )?;
+/// Accounts for selling one fill out of a listing's vault. The listing authority stands in for
+/// the seller on Auction House, so every fill gets its own seller trade state sized to the order
+/// instead of the whole listing sharing one that only matches `token_size`.
+pub(crate) struct VaultSale<'info> {
+    pub auction_house_program: AccountInfo<'info>,
+    pub listing_config: Pubkey,
+    pub listing_authority: AccountInfo<'info>,
+    pub listing_authority_bump: u8,
+    pub listing_proceeds_account: AccountInfo<'info>,
+    pub seller: AccountInfo<'info>,
+    pub seller_payment_receipt_account: AccountInfo<'info>,
+    pub buyer: AccountInfo<'info>,
+    pub buyer_refund_account: AccountInfo<'info>,
+    pub token_account: AccountInfo<'info>,
+    pub token_mint: AccountInfo<'info>,
+    pub metadata: AccountInfo<'info>,
+    pub treasury_mint: AccountInfo<'info>,
+    pub escrow_payment_account: AccountInfo<'info>,
+    pub buyer_receipt_token_account: AccountInfo<'info>,
+    pub authority: AccountInfo<'info>,
+    pub auctioneer_authority: AccountInfo<'info>,
+    pub auction_house: AccountInfo<'info>,
+    pub auction_house_fee_account: AccountInfo<'info>,
+    pub auction_house_treasury: AccountInfo<'info>,
+    pub buyer_trade_state: AccountInfo<'info>,
+    pub seller_trade_state: AccountInfo<'info>,
+    pub free_trade_state: AccountInfo<'info>,
+    pub ah_auctioneer_pda: AccountInfo<'info>,
+    pub program_as_signer: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub ata_program: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+}
 
-    // For native purchases, verify that the amount in escrow is sufficient to actually purchase the
-    // token.  This is intended to cover the migration from pre-rent-exemption checked accounts to
-    // rent-exemption checked accounts.  The fee payer makes up the shortfall up to the amount of
-    // rent for an empty account.
-    if is_native {
-        let rent_shortfall =
-            verify_withdrawal(escrow_payment_account.to_account_info(), buyer_price)?;
-        if rent_shortfall > 0 {
-            invoke_signed(
-                &system_instruction::transfer(
-                    fee_payer.key,
-                    escrow_payment_account.key,
-                    rent_shortfall,
-                ),
-                &[
-                    fee_payer.to_account_info(),
-                    escrow_payment_account.to_account_info(),
-                    system_program.to_account_info(),
-                ],
-                &[fee_payer_seeds],
-            )?;
+impl<'info> VaultSale<'info> {
+    /// Collects the accounts for a fill of `order_size` units out of `listing_config`'s vault,
+    /// once the listing has that many units for sale at `now`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        listing_config: &Account<'info, ListingConfig>,
+        order_size: u64,
+        now: UnixTimestamp,
+        auction_house_program: AccountInfo<'info>,
+        listing_authority: AccountInfo<'info>,
+        listing_proceeds_account: AccountInfo<'info>,
+        seller: AccountInfo<'info>,
+        seller_payment_receipt_account: AccountInfo<'info>,
+        buyer: AccountInfo<'info>,
+        buyer_refund_account: AccountInfo<'info>,
+        token_account: AccountInfo<'info>,
+        token_mint: AccountInfo<'info>,
+        metadata: AccountInfo<'info>,
+        treasury_mint: AccountInfo<'info>,
+        escrow_payment_account: AccountInfo<'info>,
+        buyer_receipt_token_account: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        auctioneer_authority: AccountInfo<'info>,
+        auction_house: AccountInfo<'info>,
+        auction_house_fee_account: AccountInfo<'info>,
+        auction_house_treasury: AccountInfo<'info>,
+        buyer_trade_state: AccountInfo<'info>,
+        seller_trade_state: AccountInfo<'info>,
+        free_trade_state: AccountInfo<'info>,
+        ah_auctioneer_pda: AccountInfo<'info>,
+        program_as_signer: AccountInfo<'info>,
+        token_program: AccountInfo<'info>,
+        system_program: AccountInfo<'info>,
+        ata_program: AccountInfo<'info>,
+        rent: AccountInfo<'info>,
+    ) -> Result<Self> {
+        // Continuous listings can only sell what has been emitted so far.
+        if order_size > listing_config.available_supply(now) {
+            return err!(AuctioneerError::InsufficientEmission);
         }
+        Ok(Self {
+            auction_house_program,
+            listing_config: listing_config.key(),
+            listing_authority,
+            listing_authority_bump: listing_config.listing_authority_bump,
+            listing_proceeds_account,
+            seller,
+            seller_payment_receipt_account,
+            buyer,
+            buyer_refund_account,
+            token_account,
+            token_mint,
+            metadata,
+            treasury_mint,
+            escrow_payment_account,
+            buyer_receipt_token_account,
+            authority,
+            auctioneer_authority,
+            auction_house,
+            auction_house_fee_account,
+            auction_house_treasury,
+            buyer_trade_state,
+            seller_trade_state,
+            free_trade_state,
+            ah_auctioneer_pda,
+            program_as_signer,
+            token_program,
+            system_program,
+            ata_program,
+            rent,
+        })
     }
 
-    if metadata.data_is_empty() {
-        return Err(AuctionHouseError::MetadataDoesntExist.into());
-    }
-
-    let auction_house_key = auction_house.key();
-    let wallet_key = buyer.key();
-    let escrow_signer_seeds = [
-        PREFIX.as_bytes(),
-        auction_house_key.as_ref(),
-        wallet_key.as_ref(),
-        &[escrow_payment_bump],
-    ];
-
-    let ah_seeds = [
-        PREFIX.as_bytes(),
-        auction_house.creator.as_ref(),
-        auction_house.treasury_mint.as_ref(),
-        &[auction_house.bump],
-    ];
+    /// Lists `order_size` units from the vault on Auction House, settles them against the
+    /// buyer's bid of `buyer_price`, and splits the proceeds: the buyer gets back what the bid
+    /// is above the curve price `price`, the seller gets the rest.
+    ///
+    /// Auction House executes at `buyer_price` because its trade states are derived from the
+    /// price, and a trade state derived from the curve price at settlement could not be known
+    /// when the transaction was built. Its fee and royalties are therefore charged on
+    /// `buyer_price`, and the refund comes out of the seller's share.
+    ///
+    /// Auction House charges the seller trade state rent to the listing authority, so the buyer
+    /// fronts it. Auction House 1.2.4 never closes trade states on execute sale: it only does so
+    /// when the vault balance it read before the transfer is zero, which it cannot be. Both are
+    /// cancelled here after every fill, the final one included, and their rent refunded.
+    ///
+    /// Returns how Auction House split `buyer_price`, measured from the balances it paid into,
+    /// with the refund taken off the seller's proceeds.
+    #[allow(clippy::too_many_arguments)]
+    #[inline(never)]
+    pub(crate) fn settle(
+        &self,
+        remaining_accounts: &[AccountInfo<'info>],
+        escrow_payment_bump: u8,
+        free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        auctioneer_authority_bump: u8,
+        seller_trade_state_bump: u8,
+        buyer_price: u64,
+        price: u64,
+        order_size: u64,
+    ) -> Result<SettledSale> {
+        if buyer_price < price {
+            return err!(AuctioneerError::BidBelowCurvePrice);
+        }
+        let native = self.treasury_mint.key() == spl_token::native_mint::id();
+        let expected_proceeds_account = if native {
+            self.listing_authority.key()
+        } else {
+            get_associated_token_address(&self.listing_authority.key(), &self.treasury_mint.key())
+        };
+        assert_keys_equal(self.listing_proceeds_account.key(), expected_proceeds_account)?;
+        let refund = buyer_price - price;
+        if native {
+            if self.buyer_refund_account.key() != self.buyer.key() {
+                return err!(AuctioneerError::InvalidBuyerRefundAccount);
+            }
+        } else {
+            let refund_account = Account::<TokenAccount>::try_from(&self.buyer_refund_account)?;
+            if refund_account.owner != self.buyer.key()
+                || refund_account.mint != self.treasury_mint.key()
+            {
+                return err!(AuctioneerError::InvalidBuyerRefundAccount);
+            }
+        }
 
-    // with the native account, the escrow is its own owner,
-    // whereas with token, it is the auction house that is owner.
-    let signer_seeds_for_royalties = if is_native {
-        escrow_signer_seeds
-    } else {
-        ah_seeds
-    };
+        let ah_key = self.auction_house.key();
+        let auctioneer_seeds = [
+            AUCTIONEER.as_bytes(),
+            ah_key.as_ref(),
+            &[auctioneer_authority_bump],
+        ];
+        let listing_authority_seeds = [
+            LISTING_AUTHORITY.as_bytes(),
+            self.listing_config.as_ref(),
+            &[self.listing_authority_bump],
+        ];
+        let listing_authority_signer = [&listing_authority_seeds[..]];
+
+        let fronted_rent = if self.seller_trade_state.data_is_empty() {
+            let rent = Rent::get()?.minimum_balance(TRADE_STATE_SIZE);
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.clone(),
+                    system_program::Transfer {
+                        from: self.buyer.clone(),
+                        to: self.listing_authority.clone(),
+                    },
+                ),
+                rent,
+            )?;
+            rent
+        } else {
+            0
+        };
+
+        let sell_accounts = AHSell {
+            wallet: self.listing_authority.clone(),
+            token_account: self.token_account.clone(),
+            metadata: self.metadata.clone(),
+            authority: self.authority.clone(),
+            auctioneer_authority: self.auctioneer_authority.clone(),
+            auction_house: self.auction_house.clone(),
+            auction_house_fee_account: self.auction_house_fee_account.clone(),
+            seller_trade_state: self.seller_trade_state.clone(),
+            free_seller_trade_state: self.free_trade_state.clone(),
+            ah_auctioneer_pda: self.ah_auctioneer_pda.clone(),
+            program_as_signer: self.program_as_signer.clone(),
+            token_program: self.token_program.clone(),
+            system_program: self.system_program.clone(),
+            rent: self.rent.clone(),
+        };
+        let sell_data = mpl_auction_house::instruction::AuctioneerSell {
+            trade_state_bump: seller_trade_state_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            token_size: order_size,
+        };
+        invoke_auction_house(
+            &self.auction_house_program,
+            &sell_accounts,
+            &[],
+            sell_data.data(),
+            &[&auctioneer_seeds, &listing_authority_seeds],
+        )?;
 
-    let buyer_leftover_after_royalties = pay_creator_fees(
-        &mut remaining_accounts.iter(),
-        &metadata_clone,
-        &escrow_clone,
-        &auction_house_clone,
-        &fee_payer_clone,
-        treasury_mint,
-        &ata_clone,
-        &token_clone,
-        &sys_clone,
-        &rent_clone,
-        &signer_seeds_for_royalties,
-        fee_payer_seeds,
-        buyer_price,
-        is_native,
-    )?;
+        let execute_sale_accounts = AHExecuteSale {
+            buyer: self.buyer.clone(),
+            seller: self.listing_authority.clone(),
+            token_account: self.token_account.clone(),
+            token_mint: self.token_mint.clone(),
+            metadata: self.metadata.clone(),
+            treasury_mint: self.treasury_mint.clone(),
+            escrow_payment_account: self.escrow_payment_account.clone(),
+            seller_payment_receipt_account: self.listing_proceeds_account.clone(),
+            buyer_receipt_token_account: self.buyer_receipt_token_account.clone(),
+            authority: self.authority.clone(),
+            auctioneer_authority: self.auctioneer_authority.clone(),
+            auction_house: self.auction_house.clone(),
+            auction_house_fee_account: self.auction_house_fee_account.clone(),
+            auction_house_treasury: self.auction_house_treasury.clone(),
+            buyer_trade_state: self.buyer_trade_state.clone(),
+            seller_trade_state: self.seller_trade_state.clone(),
+            free_trade_state: self.free_trade_state.clone(),
+            ah_auctioneer_pda: self.ah_auctioneer_pda.clone(),
+            token_program: self.token_program.clone(),
+            system_program: self.system_program.clone(),
+            ata_program: self.ata_program.clone(),
+            program_as_signer: self.program_as_signer.clone(),
+            rent: self.rent.clone(),
+        };
+        let treasury_before = balance_of(&self.auction_house_treasury, native)?;
+        let proceeds_before = balance_of(&self.listing_proceeds_account, native)?;
+        auctioneer_execute_sale_logic(
+            self.auction_house_program.clone(),
+            execute_sale_accounts,
+            remaining_accounts,
+            escrow_payment_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            auctioneer_authority_bump,
+            buyer_price,
+            order_size,
+        )?;
+        let marketplace_fee = balance_of(&self.auction_house_treasury, native)?
+            .saturating_sub(treasury_before);
+        let proceeds = balance_of(&self.listing_proceeds_account, native)?
+            .saturating_sub(proceeds_before);
+        let royalties = buyer_price
+            .saturating_sub(marketplace_fee)
+            .saturating_sub(proceeds);
+        // Fees and royalties on the over-bid can leave less than the refund for the seller.
+        let seller_proceeds = proceeds
+            .checked_sub(refund)
+            .ok_or(AuctioneerError::RefundExceedsProceeds)?;
+
+        // Auction House leaves the seller trade state open, even after the final fill.
+        if self.seller_trade_state.lamports() > 0 {
+            let cancel_accounts = AHCancel {
+                wallet: self.listing_authority.clone(),
+                token_account: self.token_account.clone(),
+                token_mint: self.token_mint.clone(),
+                authority: self.authority.clone(),
+                auctioneer_authority: self.auctioneer_authority.clone(),
+                auction_house: self.auction_house.clone(),
+                auction_house_fee_account: self.auction_house_fee_account.clone(),
+                trade_state: self.seller_trade_state.clone(),
+                ah_auctioneer_pda: self.ah_auctioneer_pda.clone(),
+                token_program: self.token_program.clone(),
+            };
+            let cancel_data = mpl_auction_house::instruction::AuctioneerCancel {
+                buyer_price: AUCTIONEER_BUYER_PRICE,
+                token_size: order_size,
+            };
+            invoke_auction_house(
+                &self.auction_house_program,
+                &cancel_accounts,
+                &[],
+                cancel_data.data(),
+                &[&auctioneer_seeds, &listing_authority_seeds],
+            )?;
 
-    let auction_house_fee_paid = pay_auction_house_fees(
-        auction_house,
-        &treasury_clone,
-        &escrow_clone,
-        &token_clone,
-        &sys_clone,
-        &signer_seeds_for_royalties,
-        buyer_price,
-        is_native,
-    )?;
+            if fronted_rent > 0 {
+                system_program::transfer(
+                    CpiContext::new_with_signer(
+                        self.system_program.clone(),
+                        system_program::Transfer {
+                            from: self.listing_authority.clone(),
+                            to: self.buyer.clone(),
+                        },
+                        &listing_authority_signer,
+                    ),
+                    fronted_rent,
+                )?;
+            }
+        }
 
-    let buyer_leftover_after_royalties_and_house_fee = buyer_leftover_after_royalties
-        .checked_sub(auction_house_fee_paid)
-        .ok_or(AuctionHouseError::NumericalOverflow)?;
-
-    if !is_native {
-        if seller_payment_receipt_account.data_is_empty() {
-            make_ata(
-                seller_payment_receipt_account.to_account_info(),
-                seller.to_account_info(),
-                treasury_mint.to_account_info(),
-                fee_payer.to_account_info(),
-                ata_program.to_account_info(),
-                token_program.to_account_info(),
-                system_program.to_account_info(),
-                rent.to_account_info(),
-                fee_payer_seeds,
+        // The same goes for the buyer trade state, which would otherwise stay funded and
+        // match the next fill of the same size and price.
+        if self.buyer_trade_state.lamports() > 0 {
+            let cancel_accounts = AHCancel {
+                wallet: self.buyer.clone(),
+                token_account: self.token_account.clone(),
+                token_mint: self.token_mint.clone(),
+                authority: self.authority.clone(),
+                auctioneer_authority: self.auctioneer_authority.clone(),
+                auction_house: self.auction_house.clone(),
+                auction_house_fee_account: self.auction_house_fee_account.clone(),
+                trade_state: self.buyer_trade_state.clone(),
+                ah_auctioneer_pda: self.ah_auctioneer_pda.clone(),
+                token_program: self.token_program.clone(),
+            };
+            auctioneer_cancel_logic(
+                self.auction_house_program.clone(),
+                cancel_accounts,
+                auctioneer_authority_bump,
+                buyer_price,
+                order_size,
             )?;
         }
 
-        let seller_rec_acct = assert_is_ata(
-            &seller_payment_receipt_account.to_account_info(),
-            &seller.key(),
-            &treasury_mint.key(),
-        )?;
-
-        // make sure you cant get rugged
-        if seller_rec_acct.delegate.is_some() {
-            return Err(AuctionHouseError::SellerATACannotHaveDelegate.into());
+        if native {
+            // The listing authority holds nothing but the proceeds once the rent is refunded.
+            let proceeds = self.listing_authority.lamports();
+            for (to, amount) in [
+                (&self.buyer_refund_account, refund),
+                (&self.seller, proceeds - refund),
+            ] {
+                if amount > 0 {
+                    system_program::transfer(
+                        CpiContext::new_with_signer(
+                            self.system_program.clone(),
+                            system_program::Transfer {
+                                from: self.listing_authority.clone(),
+                                to: to.clone(),
+                            },
+                            &listing_authority_signer,
+                        ),
+                        amount,
+                    )?;
+                }
+            }
+        } else {
+            let receipt = Account::<TokenAccount>::try_from(&self.seller_payment_receipt_account)?;
+            if receipt.owner != self.seller.key() || receipt.mint != self.treasury_mint.key() {
+                return err!(AuctioneerError::InvalidSellerReceipt);
+            }
+            let proceeds = Account::<TokenAccount>::try_from(&self.listing_proceeds_account)?.amount;
+            for (to, amount) in [
+                (&self.buyer_refund_account, refund),
+                (&self.seller_payment_receipt_account, proceeds - refund),
+            ] {
+                if amount > 0 {
+                    token::transfer(
+                        CpiContext::new_with_signer(
+                            self.token_program.clone(),
+                            token::Transfer {
+                                from: self.listing_proceeds_account.clone(),
+                                to: to.clone(),
+                                authority: self.listing_authority.clone(),
+                            },
+                            &listing_authority_signer,
+                        ),
+                        amount,
+                    )?;
+                }
+            }
         }
 
-        invoke_signed(
-            &spl_token::instruction::transfer(
-                token_program.key,
-                &escrow_payment_account.key(),
-                &seller_payment_receipt_account.key(),
-                &auction_house.key(),
-                &[],
-                buyer_leftover_after_royalties_and_house_fee,
-            )?,
-            &[
-                escrow_payment_account.to_account_info(),
-                seller_payment_receipt_account.to_account_info(),
-                token_program.to_account_info(),
-                auction_house.to_account_info(),
-            ],
-            &[&ah_seeds],
-        )?;
-    } else {
-        assert_keys_equal(seller_payment_receipt_account.key(), seller.key())?;
-        invoke_signed(
-            &system_instruction::transfer(
-                escrow_payment_account.key,
-                seller_payment_receipt_account.key,
-                buyer_leftover_after_royalties_and_house_fee,
-            ),
-            &[
-                escrow_payment_account.to_account_info(),
-                seller_payment_receipt_account.to_account_info(),
-                system_program.to_account_info(),
-            ],
-            &[&escrow_signer_seeds],
-        )?;
+        Ok(SettledSale {
+            seller_proceeds,
+            marketplace_fee,
+            royalties,
+        })
     }
+}
 
-    if buyer_receipt_token_account.data_is_empty() {
-        make_ata(
-            buyer_receipt_token_account.to_account_info(),
-            buyer.to_account_info(),
-            token_mint.to_account_info(),
-            fee_payer.to_account_info(),
-            ata_program.to_account_info(),
-            token_program.to_account_info(),
-            system_program.to_account_info(),
-            rent.to_account_info(),
-            fee_payer_seeds,
-        )?;
+/// How Auction House split the price of a fill.
+pub(crate) struct SettledSale {
+    pub seller_proceeds: u64,
+    pub marketplace_fee: u64,
+    pub royalties: u64,
+}
+
+// Lamports for native treasury mints, token amount otherwise.
+fn balance_of(account: &AccountInfo, native: bool) -> Result<u64> {
+    if native {
+        Ok(account.lamports())
     } else {
-        let data = buyer_receipt_token_account.try_borrow_data()?;
-        let token_account = TokenAccount::try_deserialize(&mut data.as_ref())?;
-        if &token_account.owner != buyer.key {
-            return Err(AuctionHouseError::IncorrectOwner.into());
-        }
+        Ok(Account::<TokenAccount>::try_from(account)?.amount)
     }
+}
 
-    let buyer_rec_acct = assert_is_ata(&buyer_receipt_clone, &buyer.key(), &token_mint.key())?;
-
-    // make sure you cant get rugged
-    if buyer_rec_acct.delegate.is_some() {
-        return Err(AuctionHouseError::BuyerATACannotHaveDelegate.into());
+/// Books a settled fill on the listing and emits `SaleExecuted`, followed by `AuctionSoldOut`
+/// when it took the last units.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn record_fill(
+    listing_config: &mut Account<ListingConfig>,
+    buyer: Pubkey,
+    seller: Pubkey,
+    order_size: u64,
+    price: u64,
+    sale: SettledSale,
+    now: UnixTimestamp,
+) -> Result<()> {
+    listing_config.record_sale(order_size)?;
+    emit!(SaleExecuted {
+        listing_config: listing_config.key(),
+        buyer,
+        seller,
+        order_size,
+        price,
+        items_sold: listing_config.items_sold,
+        marketplace_fee: sale.marketplace_fee,
+        royalties: sale.royalties,
+        seller_proceeds: sale.seller_proceeds,
+        timestamp: now,
+    });
+    if listing_config.is_sold_out() {
+        emit!(AuctionSoldOut {
+            listing_config: listing_config.key(),
+            items_sold: listing_config.items_sold,
+            timestamp: now,
+        });
     }
+    Ok(())
+}
 
-    let program_as_signer_seeds = [
-        PREFIX.as_bytes(),
-        SIGNER.as_bytes(),
-        &[program_as_signer_bump],
+/// Settles the sale by invoking Auction House's `auctioneer_execute_sale`, which moves the
+/// escrowed funds to the seller (minus royalties and fees) and the tokens to the buyer.
+/// Creator accounts for royalties are forwarded from `remaining_accounts`.
+#[allow(clippy::too_many_arguments)]
+#[inline(never)]
+pub(crate) fn auctioneer_execute_sale_logic<'info>(
+    cpi_program: AccountInfo<'info>,
+    cpi_accounts: AHExecuteSale<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    escrow_payment_bump: u8,
+    free_trade_state_bump: u8,
+    program_as_signer_bump: u8,
+    auctioneer_authority_bump: u8,
+    buyer_price: u64,
+    order_size: u64,
+) -> Result<()> {
+    let execute_sale_data = mpl_auction_house::instruction::AuctioneerExecuteSale {
+        escrow_payment_bump,
+        _free_trade_state_bump: free_trade_state_bump,
+        program_as_signer_bump,
+        buyer_price,
+        token_size: order_size,
+    };
+
+    let ah_key = cpi_accounts.auction_house.key();
+    let auctioneer_seeds = [
+        AUCTIONEER.as_bytes(),
+        ah_key.as_ref(),
+        &[auctioneer_authority_bump],
     ];
 
-    invoke_signed(
-        &spl_token::instruction::transfer(
-            token_program.key,
-            &token_account.key(),
-            &buyer_receipt_token_account.key(),
-            &program_as_signer.key(),
-            &[],
-            order_size,
-        )?,
-        &[
-            token_account.to_account_info(),
-            buyer_receipt_clone,
-            program_as_signer.to_account_info(),
-            token_clone,
-        ],
-        &[&program_as_signer_seeds],
+    invoke_auction_house(
+        &cpi_program,
+        &cpi_accounts,
+        remaining_accounts,
+        execute_sale_data.data(),
+        &[&auctioneer_seeds],
     )?;
-    let token_account_data = token_account;
-    if token_account_data.amount == 0 {
-        if seller.to_account_info().is_signer {
-            invoke(
-                &revoke(
-                    &token_program.key(),
-                    &token_account.key(),
-                    &seller.key(),
-                    &[],
-                )?,
-                &[
-                    token_program.to_account_info(),
-                    token_account.to_account_info(),
-                    seller.to_account_info(),
-                ],
-            )?;
-        }
-        
-        let curr_seller_lamp = seller_trade_state.lamports();
-        **seller_trade_state.lamports.borrow_mut() = 0;
-        sol_memset(&mut *seller_ts_data, 0, TRADE_STATE_SIZE);
-
-        **fee_payer.lamports.borrow_mut() = fee_payer
-            .lamports()
-            .checked_add(curr_seller_lamp)
-            .ok_or(AuctionHouseError::NumericalOverflow)?;
-
-        let curr_buyer_lamp = buyer_trade_state.lamports();
-        **buyer_trade_state.lamports.borrow_mut() = 0;
-        sol_memset(&mut *buyer_ts_data, 0, TRADE_STATE_SIZE);
-        **fee_payer.lamports.borrow_mut() = fee_payer
-            .lamports()
-            .checked_add(curr_buyer_lamp)
-            .ok_or(AuctionHouseError::NumericalOverflow)?;
-
-        if free_trade_state.lamports() > 0 {
-            let curr_buyer_lamp = free_trade_state.lamports();
-            **free_trade_state.lamports.borrow_mut() = 0;
-
-            **fee_payer.lamports.borrow_mut() = fee_payer
-                .lamports()
-                .checked_add(curr_buyer_lamp)
-                .ok_or(AuctionHouseError::NumericalOverflow)?;
-            sol_memset(
-                *free_trade_state.try_borrow_mut_data()?,
-                0,
-                TRADE_STATE_SIZE,
-            );
-        }
-    }
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,213 +1,415 @@
 use anchor_lang::{prelude::*,
     AnchorDeserialize,
     InstructionData,
-    solana_program::{self, clock::UnixTimestamp, 
-    system_instruction, 
-    program::{invoke, invoke_signed},
-    program_memory::sol_memset,
-   
-}};
+    solana_program::clock::UnixTimestamp,
+};
 pub mod util;
-use crate::util::{assert_keys_equal, assert_auction_active, assert_valid_auctioneer};
+use crate::util::{
+    assert_auction_active, assert_covers_reserve, assert_end_time_in_future, assert_valid_auctioneer,
+    assert_valid_curve, assert_valid_order_size, init_associated_token_account,
+    invoke_auction_house,
+};
 pub mod errors;
 use crate::errors::*;
+pub mod events;
+use crate::events::*;
+pub mod execute_sale;
+use execute_sale::*;
+pub mod cancel;
+use cancel::*;
+pub mod withdraw;
+use withdraw::*;
+pub mod buy;
+use buy::*;
+pub mod quote;
+use quote::*;
+pub mod migrate;
+use migrate::*;
+pub mod update_listing;
+use update_listing::*;
 
-use anchor_spl::token::{Token, Mint, TokenAccount};
+use anchor_spl::{
+    associated_token::{self, AssociatedToken},
+    token::{self, Token, Mint, TokenAccount},
+};
 use mpl_auction_house::{
     self,
     constants::{AUCTIONEER, FEE_PAYER, PREFIX, SIGNER, TRADE_STATE_SIZE},
     program::AuctionHouse as AuctionHouseProgram,
-    cpi::accounts::AuctioneerSell as AHSell,
+    cpi::accounts::{
+        AuctioneerBuy as AHBuy,
+        AuctioneerCancel as AHCancel,
+        AuctioneerExecuteSale as AHExecuteSale,
+        AuctioneerSell as AHSell,
+        AuctioneerWithdraw as AHWithdraw,
+    },
     AuctionHouse, errors::AuctionHouseError,
     Auctioneer,
-    utils::*,
 };
 pub mod math;
 use math::*;
 pub const LISTING_CONFIG: &str = "listing_config";
+pub const LISTING_AUTHORITY: &str = "listing_authority";
+pub const LISTING_CONFIG_RESERVED: usize = 64;
 pub const AUCTIONEER_BUYER_PRICE: u64 = u64::MAX;
+pub const BASIS_POINTS: u64 = 10_000;
+pub const SECONDS_PER_HOUR: u64 = 3_600;
+/// Decimal places prices are computed at before rounding to whole units.
+pub const PRICE_SCALE: u8 = 15;
+/// Upper bound for `decay_const`, equal to λ = 1 per second.
+pub const MAX_DECAY_CONSTANT: u64 = BASIS_POINTS * SECONDS_PER_HOUR;
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
 
 #[program]
 pub mod gda {
     use super::*;
+    /// Creates a listing of `token_size` units. The tokens move into a vault owned by the
+    /// listing authority PDA, which sells them on Auction House one fill at a time, so buyers
+    /// can take any `order_size` up to what is left.
+    #[allow(clippy::too_many_arguments)]
     pub fn auctioneer_sell<'info>(
         ctx: Context<'_, '_, '_, 'info, AuctioneerSell<'info>>,
-        trade_state_bump: u8,
-        free_trade_state_bump: u8,
-        program_as_signer_bump: u8,
-        auctioneer_authority_bump: u8,
         token_size: u64,
-        //start_time: UnixTimestamp,
+        start_time: UnixTimestamp,
         end_time: UnixTimestamp,
         start_price: u64,
-        decay_constant: u8,
+        reserve_price: u64,
+        decay_constant: u64,
         scale_factor: u64,
-        
+        mode: ListingMode,
+        emission_rate: u64,
     ) -> Result<()> {
+        if token_size == 0 {
+            return err!(AuctioneerError::InvalidTokenSize);
+        }
+        if ctx.accounts.token_account.amount < token_size {
+            return err!(AuctioneerError::InsufficientTokenBalance);
+        }
+        assert_valid_curve(
+            start_price,
+            reserve_price,
+            decay_constant,
+            scale_factor,
+            mode,
+            emission_rate,
+        )?;
+        let now = Clock::get()?.unix_timestamp;
+        assert_end_time_in_future(end_time, now)?;
+        // A start time in the past starts the auction right away.
+        let start_time = start_time.max(now);
+        if start_time >= end_time {
+            return err!(AuctioneerError::InvalidAuctionWindow);
+        }
+        assert_valid_auctioneer(
+            &ctx.accounts.auction_house,
+            &ctx.accounts.auctioneer_authority.key(),
+            &ctx.accounts.ah_auctioneer_pda,
+        )?;
+        ctx.accounts.listing_config.version = ListingConfig::VERSION;
         ctx.accounts.listing_config.token_size = token_size;
-        ctx.accounts.listing_config.first_init_timestamp = Clock::get()?.unix_timestamp;
+        ctx.accounts.listing_config.first_init_timestamp = start_time;
         ctx.accounts.listing_config.end_timestamp = end_time;
         ctx.accounts.listing_config.start_price = start_price;
+        ctx.accounts.listing_config.reserve_price = reserve_price;
         ctx.accounts.listing_config.decay_const = decay_constant; 
         ctx.accounts.listing_config.scale_factor = scale_factor;
         ctx.accounts.listing_config.items_sold = 0;
+        ctx.accounts.listing_config.mode = mode;
+        ctx.accounts.listing_config.emission_rate = emission_rate;
         ctx.accounts.listing_config.bump = *ctx
             .bumps
             .get("listing_config")
             .ok_or(AuctioneerError::BumpSeedNotInHashMap)?;
-    
-        let cpi_program = ctx.accounts.auction_house_program.to_account_info();
-        let cpi_accounts = AHSell {
-            wallet: ctx.accounts.wallet.to_account_info(),
-            token_account: ctx.accounts.token_account.to_account_info(),
-            metadata: ctx.accounts.metadata.to_account_info(),
-            auction_house: ctx.accounts.auction_house.to_account_info(),
-            auction_house_fee_account: ctx.accounts.auction_house_fee_account.to_account_info(),
-            seller_trade_state: ctx.accounts.seller_trade_state.to_account_info(),
-            free_seller_trade_state: ctx.accounts.free_seller_trade_state.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
-            auctioneer_authority: ctx.accounts.auctioneer_authority.to_account_info(),
-            ah_auctioneer_pda: ctx.accounts.ah_auctioneer_pda.to_account_info(),
-            token_program: ctx.accounts.token_program.to_account_info(),
-            system_program: ctx.accounts.system_program.to_account_info(),
-            program_as_signer: ctx.accounts.program_as_signer.to_account_info(),
-            rent: ctx.accounts.rent.to_account_info(),
-        };
-    
-        let sell_data = mpl_auction_house::instruction::AuctioneerSell {
-            trade_state_bump,
-            free_trade_state_bump,
-            program_as_signer_bump,
+        ctx.accounts.listing_config.listing_authority_bump = *ctx
+            .bumps
+            .get("listing_authority")
+            .ok_or(AuctioneerError::BumpSeedNotInHashMap)?;
+
+        // Auction House only sells from the seller's associated token account, and the
+        // listing authority is the seller of every fill.
+        init_associated_token_account(
+            ctx.accounts.ata_program.to_account_info(),
+            associated_token::Create {
+                payer: ctx.accounts.wallet.to_account_info(),
+                associated_token: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.listing_authority.to_account_info(),
+                mint: ctx.accounts.token_mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+        )?;
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.token_account.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.wallet.to_account_info(),
+                },
+            ),
             token_size,
-        };
-    
-        let ix = solana_program::instruction::Instruction {
-            program_id: cpi_program.key(),
-            accounts: cpi_accounts
-                .to_account_metas(None)
-                .into_iter()
-                .zip(cpi_accounts.to_account_infos())
-                .map(|mut pair| {
-                    pair.0.is_signer = pair.1.is_signer;
-                    if pair.0.pubkey == ctx.accounts.auctioneer_authority.key() {
-                        pair.0.is_signer = true;
-                    }
-                    pair.0
-                })
-                .collect(),
-            data: sell_data.data(),
-        };
-    
-        let auction_house = &ctx.accounts.auction_house;
-        let ah_key = auction_house.key();
-        let auctioneer_authority = &ctx.accounts.auctioneer_authority;
-        let _aa_key = auctioneer_authority.key();
-    
-        let auctioneer_seeds = [
-            AUCTIONEER.as_bytes(),
-            ah_key.as_ref(),
-            &[auctioneer_authority_bump],
-        ];
-    
-        invoke_signed(&ix, &cpi_accounts.to_account_infos(), &[&auctioneer_seeds])?;
-    
+        )?;
+
+        // Auction House pays the seller's share of SPL sales into the listing authority's
+        // treasury mint account, which has to exist before the first fill.
+        if ctx.accounts.treasury_mint.key() != spl_token::native_mint::id() {
+            init_associated_token_account(
+                ctx.accounts.ata_program.to_account_info(),
+                associated_token::Create {
+                    payer: ctx.accounts.wallet.to_account_info(),
+                    associated_token: ctx.accounts.listing_proceeds_account.to_account_info(),
+                    authority: ctx.accounts.listing_authority.to_account_info(),
+                    mint: ctx.accounts.treasury_mint.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+            )?;
+        }
+
+        let listing_config = &ctx.accounts.listing_config;
+        emit!(ListingCreated {
+            listing_config: listing_config.key(),
+            seller: ctx.accounts.wallet.key(),
+            token_mint: ctx.accounts.token_mint.key(),
+            treasury_mint: ctx.accounts.treasury_mint.key(),
+            token_size,
+            start_price,
+            reserve_price,
+            decay_const: decay_constant,
+            scale_factor,
+            mode,
+            emission_rate,
+            start_timestamp: start_time,
+            end_timestamp: end_time,
+            timestamp: now,
+        });
+
         Ok(())
     }
 
+    /// Bids `max_total_price` for `order_size` of the listing's `token_size` units. The escrow
+    /// holds the whole bid and `auctioneer_execute_sale` settles it at the curve price, refunding
+    /// the difference. Fails with `PriceExceedsLimit` if the current cumulative price is already
+    /// above `max_total_price`.
+    #[allow(clippy::too_many_arguments)]
     pub fn place_order<'info>(
         ctx: Context<'_,'_,'_,'info, AuctioneerBuy<'info>>,
+        trade_state_bump: u8,
+        escrow_payment_bump: u8,
+        auctioneer_authority_bump: u8,
+        _token_size: u64,
         order_size: u64,
+        max_total_price: u64,
+    ) -> Result<()> {
+        assert_auction_active(&ctx.accounts.listing_config)?;
+        assert_valid_order_size(&ctx.accounts.listing_config, order_size)?;
+        assert_covers_reserve(&ctx.accounts.listing_config, order_size, max_total_price)?;
+        let now = Clock::get()?.unix_timestamp;
+        let cumulative_price = ctx.accounts.listing_config.calculate_price_at(order_size, now)?;
+        if cumulative_price > max_total_price {
+            return err!(AuctioneerError::PriceExceedsLimit);
+        }
+        ctx.accounts.bid(
+            trade_state_bump,
+            escrow_payment_bump,
+            auctioneer_authority_bump,
+            max_total_price,
+            cumulative_price,
+            order_size,
+            now,
+        )
+    }
+
+    /// Same as `place_order`, but bids `budget` for as many units as it covers at the current
+    /// price. `token_size` is the listing's size and only serves to derive `listing_config`.
+    pub fn place_order_with_budget<'info>(
+        ctx: Context<'_,'_,'_,'info, AuctioneerBuy<'info>>,
         trade_state_bump: u8,
         escrow_payment_bump: u8,
-        //auctioneer_authority_bump: u8,
+        auctioneer_authority_bump: u8,
+        _token_size: u64,
+        budget: u64,
     ) -> Result<()> {
         assert_auction_active(&ctx.accounts.listing_config)?;
-        let cumulative_price = &ctx.accounts.listing_config.calculate_price(order_size).unwrap();
-        auctioneer_place_order_logic(
-            ctx.accounts.wallet.to_owned(),
-            ctx.accounts.payment_account.to_owned(),
-            ctx.accounts.transfer_authority.to_owned(),
-            *ctx.accounts.treasury_mint.to_owned(),
-            *ctx.accounts.token_account.to_owned(),
-            ctx.accounts.metadata.to_owned(),
-            ctx.accounts.escrow_payment_account.to_owned(),
-            &mut ctx.accounts.auction_house,
-            ctx.accounts.auction_house_fee_account.to_owned(),
-            ctx.accounts.buyer_trade_state.to_owned(),
-            ctx.accounts.authority.to_owned(),
-            ctx.accounts.auctioneer_authority.to_owned(),
-            ctx.accounts.ah_auctioneer_pda.to_owned(),
-            ctx.accounts.token_program.to_owned(),
-            ctx.accounts.system_program.to_owned(),
-            ctx.accounts.rent.to_owned(),
+        let now = Clock::get()?.unix_timestamp;
+        let order_size = ctx.accounts.listing_config.max_quantity_for_budget(budget, now)?;
+        if order_size == 0 {
+            return err!(AuctioneerError::BudgetTooLow);
+        }
+        ctx.accounts
+            .assert_buyer_trade_state(budget, order_size, trade_state_bump)?;
+        let cumulative_price = ctx.accounts.listing_config.calculate_price_at(order_size, now)?;
+        ctx.accounts.bid(
             trade_state_bump,
             escrow_payment_bump,
-            *cumulative_price,
+            auctioneer_authority_bump,
+            budget,
+            cumulative_price,
             order_size,
-            false,
-            *ctx.bumps
-                .get("escrow_payment_account")
-                .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?,
-            *ctx.bumps
-                .get("buyer_trade_state")
-                .ok_or(AuctionHouseError::BumpSeedNotInHashMap)?,
-        )?;
-        Ok(())
+            now,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn auctioneer_execute_sale<'info>(
+        ctx: Context<'_, '_, '_, 'info, AuctioneerExecuteSale<'info>>,
+        escrow_payment_bump: u8,
+        free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        auctioneer_authority_bump: u8,
+        buyer_price: u64,
+        token_size: u64,
+        order_size: u64,
+    ) -> Result<()> {
+        execute_sale::auctioneer_execute_sale(
+            ctx,
+            escrow_payment_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            auctioneer_authority_bump,
+            buyer_price,
+            token_size,
+            order_size,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn buy<'info>(
+        ctx: Context<'_, '_, '_, 'info, Buy<'info>>,
+        trade_state_bump: u8,
+        escrow_payment_bump: u8,
+        free_trade_state_bump: u8,
+        program_as_signer_bump: u8,
+        auctioneer_authority_bump: u8,
+        _token_size: u64,
+        order_size: u64,
+        max_total_price: u64,
+    ) -> Result<()> {
+        buy::buy(
+            ctx,
+            trade_state_bump,
+            escrow_payment_bump,
+            free_trade_state_bump,
+            program_as_signer_bump,
+            auctioneer_authority_bump,
+            order_size,
+            max_total_price,
+        )
+    }
+
+    pub fn auctioneer_cancel<'info>(
+        ctx: Context<'_, '_, '_, 'info, AuctioneerCancel<'info>>,
+        auctioneer_authority_bump: u8,
+        token_size: u64,
+    ) -> Result<()> {
+        cancel::auctioneer_cancel(ctx, auctioneer_authority_bump, token_size)
+    }
+
+    pub fn auctioneer_cancel_bid<'info>(
+        ctx: Context<'_, '_, '_, 'info, AuctioneerCancelBid<'info>>,
+        auctioneer_authority_bump: u8,
+        buyer_price: u64,
+        token_size: u64,
+    ) -> Result<()> {
+        cancel::auctioneer_cancel_bid(ctx, auctioneer_authority_bump, buyer_price, token_size)
+    }
+
+    pub fn auctioneer_withdraw<'info>(
+        ctx: Context<'_, '_, '_, 'info, AuctioneerWithdraw<'info>>,
+        escrow_payment_bump: u8,
+        auctioneer_authority_bump: u8,
+        amount: u64,
+    ) -> Result<()> {
+        withdraw::auctioneer_withdraw(ctx, escrow_payment_bump, auctioneer_authority_bump, amount)
+    }
+
+    pub fn quote(ctx: Context<Quote>, order_size: u64) -> Result<()> {
+        quote::quote(ctx, order_size)
+    }
+
+    pub fn migrate_listing_config(ctx: Context<MigrateListingConfig>) -> Result<()> {
+        migrate::migrate_listing_config(ctx)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_listing(
+        ctx: Context<UpdateListing>,
+        token_size: u64,
+        start_price: u64,
+        reserve_price: u64,
+        decay_constant: u64,
+        scale_factor: u64,
+        end_time: UnixTimestamp,
+    ) -> Result<()> {
+        update_listing::update_listing(
+            ctx,
+            token_size,
+            start_price,
+            reserve_price,
+            decay_constant,
+            scale_factor,
+            end_time,
+        )
     }
    
 }
 
 #[derive(Accounts)]
-#[instruction(trade_state_bump: u8, free_trade_state_bump: u8, program_as_signer_bump: u8, auctioneer_authority_bump: u8, token_size: u64)]
+#[instruction(token_size: u64)]
 pub struct AuctioneerSell<'info>{
     pub auction_house_program: Program<'info, AuctionHouseProgram>,
 
     #[account(
         init,
         payer=wallet,
-        space= std::mem::size_of::<ListingConfig>(),
+        space= ListingConfig::LEN,
         seeds=[
             LISTING_CONFIG.as_bytes(),
             wallet.key().as_ref(),
             auction_house.key().as_ref(),
-            token_account.key().as_ref(),
             auction_house.treasury_mint.as_ref(),
-            token_account.mint.as_ref(),
+            token_mint.key().as_ref(),
             &token_size.to_le_bytes(), 
         ],
         bump,
     )]
     pub listing_config: Account<'info, ListingConfig>,
 
-    /// SPL token account containing token for sale.
-    #[account(mut)]
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// PDA that owns the vault and acts as the seller on Auction House.
+    #[account(seeds=[LISTING_AUTHORITY.as_bytes(), listing_config.key().as_ref()], bump)]
+    pub listing_authority: UncheckedAccount<'info>,
+
+    /// SPL token account the listed tokens are taken from.
+    #[account(mut, constraint = token_account.mint == token_mint.key())]
     pub token_account: Box<Account<'info, TokenAccount>>,
-    
-    pub metadata: UncheckedAccount<'info>,
 
-    /// CHECK: Verified through CPI
-    /// Auction House authority account.
-    pub authority: UncheckedAccount<'info>,
+    /// Token mint account of the listed tokens.
+    pub token_mint: Box<Account<'info, Mint>>,
 
-    #[account(seeds=[PREFIX.as_bytes(), auction_house.creator.as_ref(), auction_house.treasury_mint.as_ref()], seeds::program=auction_house_program, bump=auction_house.bump, has_one=auction_house_fee_account)]
-    pub auction_house: Box<Account<'info, AuctionHouse>>,
+    /// CHECK: Created or validated in the handler as the listing authority's associated token account.
+    /// Listing authority's token account holding the unsold units.
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
 
-    #[account(mut, seeds=[PREFIX.as_bytes(), auction_house.key().as_ref(), FEE_PAYER.as_bytes()], seeds::program=auction_house_program, bump=auction_house.fee_payer_bump)]
-    pub auction_house_fee_account: UncheckedAccount<'info>,
+    /// CHECK: Created or validated in the handler for SPL treasury mints, unused for native ones.
+    /// Listing authority's treasury mint account Auction House pays the proceeds into.
+    #[account(mut)]
+    pub listing_proceeds_account: UncheckedAccount<'info>,
 
-    
-    #[account(mut, seeds=[PREFIX.as_bytes(), wallet.key().as_ref(), auction_house.key().as_ref(), token_account.key().as_ref(), auction_house.treasury_mint.as_ref(), token_account.mint.as_ref(), &u64::MAX.to_le_bytes(), &token_size.to_le_bytes()], seeds::program=auction_house_program, bump=trade_state_bump)]
-    pub seller_trade_state: UncheckedAccount<'info>,
+    /// Auction House instance treasury mint account.
+    pub treasury_mint: Box<Account<'info, Mint>>,
 
-    #[account(mut, seeds=[PREFIX.as_bytes(), wallet.key().as_ref(), auction_house.key().as_ref(), token_account.key().as_ref(), auction_house.treasury_mint.as_ref(), token_account.mint.as_ref(), &0u64.to_le_bytes(), &token_size.to_le_bytes()], seeds::program=auction_house_program, bump=free_trade_state_bump)]
-    pub free_seller_trade_state: UncheckedAccount<'info>,
+    #[account(seeds=[PREFIX.as_bytes(), auction_house.creator.as_ref(), auction_house.treasury_mint.as_ref()], seeds::program=auction_house_program, bump=auction_house.bump, has_one=treasury_mint)]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
 
-    /// CHECK: Verified through CPI
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
     /// The auctioneer program PDA running this auction.
-    /// this is the wallet owner that is the authority of the main auction house setup
-    pub auctioneer_authority: Signer<'info>,
+    #[account(seeds = [AUCTIONEER.as_bytes(), auction_house.key().as_ref()], bump)]
+    pub auctioneer_authority: UncheckedAccount<'info>,
 
     /// CHECK: Not dangerous. Account seeds checked in constraint.
     /// The auctioneer PDA owned by Auction House storing scopes.
@@ -222,14 +424,11 @@ pub struct AuctioneerSell<'info>{
     )]
     pub ah_auctioneer_pda: Account<'info, mpl_auction_house::Auctioneer>,
 
-    /// CHECK: Not dangerous. Account seeds checked in constraint.
-    #[account(seeds=[PREFIX.as_bytes(), SIGNER.as_bytes()], seeds::program=auction_house_program, bump=program_as_signer_bump)]
-    pub program_as_signer: UncheckedAccount<'info>,
-
     #[account(mut)]
-    pub wallet: UncheckedAccount<'info>,
+    pub wallet: Signer<'info>,
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, Token>,
+    pub ata_program: Program<'info, AssociatedToken>,
     pub rent: Sysvar<'info, Rent>,
 }
 #[derive(Accounts)]
@@ -246,15 +445,22 @@ pub struct AuctioneerBuy<'info> {
             LISTING_CONFIG.as_bytes(),
             seller.key().as_ref(),
             auction_house.key().as_ref(),
-            token_account.key().as_ref(),
             auction_house.treasury_mint.as_ref(),
             token_account.mint.as_ref(),
             &token_size.to_le_bytes()
         ],
-        bump,
+        bump = listing_config.bump,
     )]
     pub listing_config: Account<'info, ListingConfig>,
 
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// PDA that owns the listing's vault.
+    #[account(
+        seeds=[LISTING_AUTHORITY.as_bytes(), listing_config.key().as_ref()],
+        bump = listing_config.listing_authority_bump,
+    )]
+    pub listing_authority: UncheckedAccount<'info>,
+
     /// The seller of the NFT
     /// CHECK: Checked via trade state constraints
     pub seller: UncheckedAccount<'info>,
@@ -275,7 +481,8 @@ pub struct AuctioneerBuy<'info> {
     /// Auction House instance treasury mint account.
     treasury_mint: Box<Account<'info, Mint>>,
 
-    /// SPL token account.
+    /// The listing's vault, bids are placed against it.
+    #[account(constraint = token_account.owner == listing_authority.key())]
     token_account: Box<Account<'info, TokenAccount>>,
 
     /// CHECK: Verified through CPI
@@ -308,16 +515,16 @@ pub struct AuctioneerBuy<'info> {
     #[account(mut, seeds = [PREFIX.as_bytes(), auction_house.key().as_ref(), FEE_PAYER.as_bytes()], seeds::program=auction_house_program, bump = auction_house.fee_payer_bump)]
     auction_house_fee_account: UncheckedAccount<'info>,
 
-    /// CHECK: Not dangerous. Account seeds checked in constraint.
-    /// Buyer trade state PDA.
-    ///
-      
-    #[account(mut, seeds = [PREFIX.as_bytes(), wallet.key().as_ref(), auction_house.key().as_ref(), token_account.key().as_ref(), treasury_mint.key().as_ref(), token_account.mint.as_ref(), /*buyer_price.to_le_bytes().as_ref()*/ token_size.to_le_bytes().as_ref()], seeds::program=auction_house_program, bump = trade_state_bump)]
+    /// CHECK: Verified through CPI
+    /// Buyer trade state PDA. Its seeds include the buyer price computed on-chain
+    /// from the GDA curve, so Auction House checks the derivation.
+    #[account(mut)]
     buyer_trade_state: UncheckedAccount<'info>,
     
-    /// CHECK: Is used as a seed for ah_auctioneer_pda.
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
     /// The auctioneer program PDA running this auction.
-    pub auctioneer_authority: Signer<'info>,
+    #[account(seeds = [AUCTIONEER.as_bytes(), auction_house.key().as_ref()], bump = auctioneer_authority_bump)]
+    pub auctioneer_authority: UncheckedAccount<'info>,
 
     /// CHECK: Not dangerous. Account seeds checked in constraint.
     /// The auctioneer PDA owned by Auction House storing scopes.
@@ -336,47 +543,426 @@ pub struct AuctioneerBuy<'info> {
     rent: Sysvar<'info, Rent>,
 }
 
+impl<'info> AuctioneerBuy<'info> {
+    /// Checks that `buyer_trade_state` is Auction House's trade state for a bid of
+    /// `buyer_price` on `order_size` units. Budget orders only learn their size on-chain, so a
+    /// caller that predicted a different size gets `OrderSizeMismatch` rather than a failed
+    /// account creation inside the CPI.
+    fn assert_buyer_trade_state(&self, buyer_price: u64, order_size: u64, trade_state_bump: u8) -> Result<()> {
+        let wallet = self.wallet.key();
+        let auction_house = self.auction_house.key();
+        let token_account = self.token_account.key();
+        let expected = Pubkey::create_program_address(
+            &[
+                PREFIX.as_bytes(),
+                wallet.as_ref(),
+                auction_house.as_ref(),
+                token_account.as_ref(),
+                self.treasury_mint.key().as_ref(),
+                self.token_account.mint.as_ref(),
+                &buyer_price.to_le_bytes(),
+                &order_size.to_le_bytes(),
+                &[trade_state_bump],
+            ],
+            &mpl_auction_house::ID,
+        )
+        .map_err(|_| AuctioneerError::OrderSizeMismatch)?;
+        if expected != self.buyer_trade_state.key() {
+            return err!(AuctioneerError::OrderSizeMismatch);
+        }
+        Ok(())
+    }
 
+    /// Bids `buyer_price` for `order_size` units through Auction House and emits `OrderPlaced`
+    /// with the `cumulative_price` they were quoted at.
+    #[allow(clippy::too_many_arguments)]
+    fn bid(
+        &self,
+        trade_state_bump: u8,
+        escrow_payment_bump: u8,
+        auctioneer_authority_bump: u8,
+        buyer_price: u64,
+        cumulative_price: u64,
+        order_size: u64,
+        now: UnixTimestamp,
+    ) -> Result<()> {
+        auctioneer_place_order_logic(
+            self.auction_house_program.to_owned(),
+            self.wallet.to_owned(),
+            self.payment_account.to_owned(),
+            self.transfer_authority.to_owned(),
+            *self.treasury_mint.to_owned(),
+            *self.token_account.to_owned(),
+            self.metadata.to_owned(),
+            self.escrow_payment_account.to_owned(),
+            &self.auction_house,
+            self.auction_house_fee_account.to_owned(),
+            self.buyer_trade_state.to_owned(),
+            self.authority.to_owned(),
+            self.auctioneer_authority.to_owned(),
+            self.ah_auctioneer_pda.to_owned(),
+            self.token_program.to_owned(),
+            self.system_program.to_owned(),
+            self.rent.to_owned(),
+            trade_state_bump,
+            escrow_payment_bump,
+            auctioneer_authority_bump,
+            buyer_price,
+            order_size,
+        )?;
+        emit!(OrderPlaced {
+            listing_config: self.listing_config.key(),
+            buyer: self.wallet.key(),
+            order_size,
+            cumulative_price,
+            buyer_price,
+            timestamp: now,
+        });
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ListingMode {
+    /// Discrete GDA: every unit is sold through its own auction, suited to NFTs.
+    Discrete,
+    /// Continuous GDA: units are emitted at `emission_rate` per second, suited to fungible tokens.
+    Continuous,
+}
 
 #[account]
 pub struct ListingConfig{
+    pub version: u8, // layout version, see ListingConfig::VERSION
     pub token_size: u64, //Total items to be sold in that auction
     pub items_sold: u64, //'m' value - cumulative of token sizes being sold (if it's the first sale of the auction) or already sold
     pub start_price: u64, //  'k' in the paradigm's equation
-    pub decay_const: u8, // lambda 
-    pub scale_factor: u64, // alpha responsible for the increase in the initial start_price
-    pub first_init_timestamp: UnixTimestamp, // timestamp of the genesis of the first auction
+    pub reserve_price: u64, // floor for the price of a single unit, 0 for none
+    pub decay_const: u64, // lambda, in basis points per hour
+    pub scale_factor: u64, // alpha responsible for the increase in the initial start_price, in basis points (10_000 = 1.0)
+    pub first_init_timestamp: UnixTimestamp, // start time of the first auction, decay is measured from here
     pub end_timestamp: UnixTimestamp, //
-    //pub last_updated_ts: UnixTimestamp, //
+    pub mode: ListingMode, // which of the paper's pricing formulas this listing uses
+    pub emission_rate: u64, // 'r', units emitted per second in continuous mode
     //pub auction_interval: u64,
     //pub auction_index: u64, // index
     pub bump: u8,
+    pub listing_authority_bump: u8, // bump of the PDA that holds the vault and sells on Auction House
+    pub reserved: [u8; LISTING_CONFIG_RESERVED], // zeroed space for fields added by later versions
 }
 impl ListingConfig{
-    pub fn calculate_price(&self, order_size: u64) -> Result<u64>{
-        let m = Decimal::from_integer(self.items_sold);
+    /// Current layout version, written by `auctioneer_sell` and `migrate_listing_config`.
+    pub const VERSION: u8 = 1;
+
+    /// Account size: discriminator, version, nine u64/i64 fields, mode, two bumps and the
+    /// reserved padding. New fields take their bytes out of `reserved` so this stays fixed.
+    pub const LEN: usize = 8 + 1 + 9 * 8 + 1 + 2 + LISTING_CONFIG_RESERVED;
+
+    /// Cumulative price of the next `order_size` units at `now`.
+    pub fn calculate_price_at(&self, order_size: u64, now: UnixTimestamp) -> Result<u64>{
+        match self.mode {
+            ListingMode::Discrete => self.calculate_discrete_price(order_size, now),
+            ListingMode::Continuous => self.calculate_continuous_price(order_size, now),
+        }
+    }
+
+    /// Whether every unit of the listing has been sold. A sold out listing stays on chain until
+    /// the seller closes it with `auctioneer_cancel`; `assert_auction_active` rejects new orders.
+    pub fn is_sold_out(&self) -> bool {
+        self.items_sold >= self.token_size
+    }
+
+    /// Books a settled fill of `order_size` units. Continuous pricing needs nothing else: the
+    /// age of the oldest available auction follows from `items_sold` and `emission_rate`.
+    pub fn record_sale(&mut self, order_size: u64) -> Result<()> {
+        self.items_sold = self
+            .items_sold
+            .checked_add(order_size)
+            .ok_or(AuctionHouseError::NumericalOverflow)?;
+        Ok(())
+    }
+
+    /// Units emitted since the auction started that have not been sold yet.
+    /// Only continuous listings emit over time; discrete listings expose everything up front.
+    pub fn available_supply(&self, now: UnixTimestamp) -> u64 {
+        match self.mode {
+            ListingMode::Discrete => self.token_size.saturating_sub(self.items_sold),
+            ListingMode::Continuous => {
+                let elapsed = now.saturating_sub(self.first_init_timestamp).max(0) as u64;
+                elapsed
+                    .saturating_mul(self.emission_rate)
+                    .min(self.token_size)
+                    .saturating_sub(self.items_sold)
+            }
+        }
+    }
+
+    /// λ per second at `scale`, converted from `decay_const` in basis points per hour.
+    pub fn decay_per_second(&self, scale: u8) -> Result<Decimal> {
+        Decimal::from_integer(self.decay_const)
+            .to_scale(scale)?
+            .div(Decimal::from_integer(BASIS_POINTS * SECONDS_PER_HOUR))
+    }
+
+    /// Alpha at `scale`, converted from `scale_factor` in basis points.
+    pub fn scale_factor_decimal(&self, scale: u8) -> Result<Decimal> {
+        Decimal::new(self.scale_factor.into(), 4).to_scale(scale)
+    }
+
+    // Seconds since the auction started, zero before it starts.
+    fn elapsed_seconds(&self, now: UnixTimestamp) -> Result<u64> {
+        let elapsed = now
+            .checked_sub(self.first_init_timestamp)
+            .ok_or(MathError::Overflow)?;
+        Ok(elapsed.max(0) as u64)
+    }
+
+    // k * a^m * (a^q - 1) / (e^(λt) * (a - 1)), evaluated as k * (a^(m+q) - a^m) / (a - 1)
+    // with every a^n * e^(-λt) computed as a single exponential in log space, so neither a^n
+    // nor e^(λt) has to fit on its own and long-running auctions decay towards zero.
+    // Unit prices rise with a, so the first j units of the order are the ones clamped to the
+    // reserve price and the curve only prices the remaining q - j, starting at a^(m+j).
+    // Intermediate products are 256-bit wide and the price is rounded up, in the seller's favor.
+    fn calculate_discrete_price(&self, order_size: u64, now: UnixTimestamp) -> Result<u64>{
+        let k = Decimal::from_integer(self.start_price);
+        let scale = PRICE_SCALE;
+        let one = Decimal::from_integer(1).to_scale(scale)?;
+        let a = self.scale_factor_decimal(scale)?;
+        let first_unit_factor = self.discrete_first_unit_factor(now, scale)?;
+        let reserve_units = self.discrete_reserve_units(first_unit_factor, order_size, scale)?;
+        let curve_start = self
+            .items_sold
+            .checked_add(reserve_units)
+            .ok_or(MathError::Overflow)?;
+        let curve_end = self
+            .items_sold
+            .checked_add(order_size)
+            .ok_or(MathError::Overflow)?;
+        let factor = self
+            .discrete_unit_factor(curve_end, now, scale)?
+            .sub(self.discrete_unit_factor(curve_start, now, scale)?)?
+            .div_up(a.sub(one)?)?;
+        let cumulative_price = k.mul_up(factor)?.to_u64()?;
+        Ok(reserve_units
+            .checked_mul(self.reserve_price)
+            .ok_or(MathError::Overflow)?
+            .checked_add(cumulative_price)
+            .ok_or(MathError::Overflow)?)
+    }
+
+    // a^m * e^(-λt), the price of the next unit as a fraction of k.
+    fn discrete_first_unit_factor(&self, now: UnixTimestamp, scale: u8) -> Result<Decimal> {
+        self.discrete_unit_factor(self.items_sold, now, scale)
+    }
+
+    // a^n * e^(-λt) = e^(n * ln(a) - λt).
+    fn discrete_unit_factor(&self, units: u64, now: UnixTimestamp, scale: u8) -> Result<Decimal> {
+        let growth = self.scale_factor_decimal(scale)?.ln()?.mul(u128::from(units))?;
+        let t = Decimal::from_integer(self.elapsed_seconds(now)?).to_scale(scale)?;
+        growth.exp_diff(self.decay_per_second(scale)?.mul(t)?)
+    }
+
+    // Number of units among the next `order_size` whose curve price is below the reserve:
+    // j = ceil(ln(reserve / (k * a^m * e^(-λt))) / ln(a)).
+    fn discrete_reserve_units(&self, first_unit_factor: Decimal, order_size: u64, scale: u8) -> Result<u64> {
+        if self.reserve_price == 0 {
+            return Ok(0);
+        }
+        let k = Decimal::from_integer(self.start_price);
+        let reserve = Decimal::from_integer(self.reserve_price).to_scale(scale)?;
+        let first_unit_price = first_unit_factor.mul(k)?;
+        if first_unit_price.val == 0 {
+            return Ok(order_size);
+        }
+        if first_unit_price.val >= reserve.val {
+            return Ok(0);
+        }
+        let a = self.scale_factor_decimal(scale)?;
+        let units = reserve
+            .div(k)?
+            .div(first_unit_factor)?
+            .ln()?
+            .div(a.ln()?)?
+            .to_scale_up(0)?;
+        Ok(units.val.min(order_size.into()) as u64)
+    }
+
+    // k * (e^(λq/r) - 1) / (λ * e^(λT)), evaluated as k * (e^(λ(q/r - T)) - e^(-λT)) / λ so that
+    // e^(λq/r) never has to fit on its own. T is the age of the oldest auction still
+    // available. Auctions are bought oldest first, so the first j of them are the ones clamped
+    // to the reserve price and the curve prices the rest from age T - j/r.
+    fn calculate_continuous_price(&self, order_size: u64, now: UnixTimestamp) -> Result<u64>{
+        if order_size > self.available_supply(now) {
+            return err!(AuctioneerError::InsufficientEmission);
+        }
+        let scale = PRICE_SCALE;
         let k = Decimal::from_integer(self.start_price);
-        let q = Decimal::from_integer(order_size);
-        let e = Decimal::euler_value();
-        let one = Decimal::from_integer(1);
-        let decay = self.decay_const as u128;
-        let now = Clock::get()?.unix_timestamp as u64;
-        let t = Decimal::from_integer(now.checked_sub(self.first_init_timestamp as u64).unwrap());
-        let a = Decimal::from_integer(self.scale_factor);
-        let num1 = k.mul(a.pow_with_accuracy(m.val));
-        let num2 = a.pow_with_accuracy(q.val).sub(one).unwrap();
-        let den1 = e.pow_with_accuracy(t.mul(decay).val);
-        let den2 = a.sub(one).unwrap();
-        let num = num1.mul(num2);
-        let den = den1.mul(den2);
-        let cumulative_price = num.div_up(den).to_scale(0).val as u64;
-        Ok(cumulative_price)
+        let lambda = self.decay_per_second(scale)?;
+        let r = Decimal::from_integer(self.emission_rate);
+        let age = self.oldest_auction_age(now, scale)?;
+        let reserve_units = self.continuous_reserve_units(age, order_size, scale)?;
+        let curve_age = age.sub(Decimal::from_integer(reserve_units).to_scale(scale)?.div(r)?)?;
+        let q_time = Decimal::from_integer(order_size - reserve_units).to_scale(scale)?.div(r)?;
+        let curve_decay = lambda.mul(curve_age)?;
+        let factor = lambda
+            .mul(q_time)?
+            .exp_diff(curve_decay)?
+            .sub(curve_decay.exp_neg()?)?
+            .div_up(lambda)?;
+        let cumulative_price = k.mul_up(factor)?.to_u64()?;
+        Ok(reserve_units
+            .checked_mul(self.reserve_price)
+            .ok_or(MathError::Overflow)?
+            .checked_add(cumulative_price)
+            .ok_or(MathError::Overflow)?)
+    }
+
+    // An auction drops below the reserve once it is older than A = ln(k / reserve) / λ, so
+    // j = ceil((T - A) * r) of the oldest auctions are clamped.
+    fn continuous_reserve_units(&self, age: Decimal, order_size: u64, scale: u8) -> Result<u64> {
+        if self.reserve_price == 0 {
+            return Ok(0);
+        }
+        if self.reserve_price >= self.start_price {
+            return Ok(order_size);
+        }
+        let floor_age = Decimal::from_integer(self.start_price)
+            .to_scale(scale)?
+            .div(Decimal::from_integer(self.reserve_price))?
+            .ln()?
+            .div(self.decay_per_second(scale)?)?;
+        if age.val <= floor_age.val {
+            return Ok(0);
+        }
+        let units = age
+            .sub(floor_age)?
+            .mul(Decimal::from_integer(self.emission_rate))?
+            .to_scale_up(0)?;
+        Ok(units.val.min(order_size.into()) as u64)
+    }
+
+    // Every sold unit consumes 1/r seconds of emissions, so T = t - m/r.
+    fn oldest_auction_age(&self, now: UnixTimestamp, scale: u8) -> Result<Decimal> {
+        let r = Decimal::from_integer(self.emission_rate);
+        let t = Decimal::from_integer(self.elapsed_seconds(now)?).to_scale(scale)?;
+        let sold_time = Decimal::from_integer(self.items_sold).to_scale(scale)?.div(r)?;
+        t.sub(sold_time)
+    }
+
+    /// The largest order size whose cumulative price at `now` fits in `budget`, capped at the
+    /// supply still available. The mode's pricing formula, reserve price included, is inverted
+    /// with `Decimal::ln`; that estimate only misses the exact size by fixed-point rounding, so
+    /// just the units either side of it are priced. Bisecting the whole supply is left for
+    /// estimates that cannot be evaluated.
+    pub fn max_quantity_for_budget(&self, budget: u64, now: UnixTimestamp) -> Result<u64> {
+        let available = self.available_supply(now);
+        let estimate = match self.mode {
+            ListingMode::Discrete => self.discrete_quantity_for_budget(budget, now),
+            ListingMode::Continuous => self.continuous_quantity_for_budget(budget, now),
+        };
+        let (low, high) = match estimate {
+            Ok(estimate) => {
+                let estimate = estimate.min(available);
+                let window = estimate.saturating_sub(1)..=estimate.saturating_add(1).min(available);
+                if let Some(order_size) = window
+                    .rev()
+                    .find(|&order_size| self.fits_budget(order_size, budget, now))
+                {
+                    return Ok(order_size);
+                }
+                (0, estimate.saturating_sub(2))
+            }
+            Err(_) => (0, available),
+        };
+        Ok(self.bisect_quantity_for_budget(low, high, budget, now))
+    }
+
+    // The largest order size in [low, high] that fits in `budget`, given that `low` does.
+    fn bisect_quantity_for_budget(&self, mut low: u64, mut high: u64, budget: u64, now: UnixTimestamp) -> u64 {
+        while low < high {
+            let mid = low + (high - low).div_ceil(2);
+            if self.fits_budget(mid, budget, now) {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        low
+    }
+
+    // Prices grow with the order size, so an order whose price overflows is above any budget.
+    fn fits_budget(&self, order_size: u64, budget: u64, now: UnixTimestamp) -> bool {
+        matches!(self.calculate_price_at(order_size, now), Ok(price) if price <= budget)
+    }
+
+    // The first j units cost the reserve price R each and the curve prices the rest from
+    // a^(m+j), so once the budget covers those j units:
+    // q = j + ln((B - j * R) * (a - 1) / (k * a^(m+j) * e^(-λt)) + 1) / ln(a)
+    fn discrete_quantity_for_budget(&self, budget: u64, now: UnixTimestamp) -> Result<u64> {
+        let scale = PRICE_SCALE;
+        let one = Decimal::from_integer(1).to_scale(scale)?;
+        let a = self.scale_factor_decimal(scale)?;
+        let first_unit_factor = self.discrete_first_unit_factor(now, scale)?;
+        let reserve_units = self.discrete_reserve_units(first_unit_factor, u64::MAX, scale)?;
+        if let Some(units) = budget.checked_div(self.reserve_price) {
+            if units <= reserve_units {
+                return Ok(units);
+            }
+        }
+        let curve_start = self
+            .items_sold
+            .checked_add(reserve_units)
+            .ok_or(MathError::Overflow)?;
+        let curve_factor = self.discrete_unit_factor(curve_start, now, scale)?;
+        if self.start_price == 0 || curve_factor.val == 0 {
+            return Ok(u64::MAX);
+        }
+        let ratio = Decimal::from_integer(budget - reserve_units * self.reserve_price)
+            .to_scale(scale)?
+            .div(Decimal::from_integer(self.start_price))?
+            .mul(a.sub(one)?)?
+            .div(curve_factor)?;
+        let quantity = ratio.add(one)?.ln()?.div(a.ln()?)?;
+        let quantity = quantity.to_scale(0)?.val.min(u64::MAX.into()) as u64;
+        Ok(quantity.saturating_add(reserve_units))
+    }
+
+    // The oldest j auctions cost the reserve price R each and the curve prices the rest from
+    // age T' = T - j/r, so once the budget covers those j auctions:
+    // q = j + r / λ * ln((B - j * R) * λ / (k * e^(-λT')) + 1)
+    fn continuous_quantity_for_budget(&self, budget: u64, now: UnixTimestamp) -> Result<u64> {
+        let scale = PRICE_SCALE;
+        let one = Decimal::from_integer(1).to_scale(scale)?;
+        let lambda = self.decay_per_second(scale)?;
+        let r = Decimal::from_integer(self.emission_rate);
+        let age = self.oldest_auction_age(now, scale)?;
+        let reserve_units = self.continuous_reserve_units(age, u64::MAX, scale)?;
+        if let Some(units) = budget.checked_div(self.reserve_price) {
+            if units <= reserve_units {
+                return Ok(units);
+            }
+        }
+        let curve_age = age.sub(Decimal::from_integer(reserve_units).to_scale(scale)?.div(r)?)?;
+        let decay_factor = lambda.mul(curve_age)?.exp_neg()?;
+        if self.start_price == 0 || decay_factor.val == 0 {
+            return Ok(u64::MAX);
+        }
+        let ratio = Decimal::from_integer(budget - reserve_units * self.reserve_price)
+            .to_scale(scale)?
+            .div(Decimal::from_integer(self.start_price))?
+            .mul(lambda)?
+            .div(decay_factor)?;
+        let quantity = ratio.add(one)?.ln()?.mul(r)?.div(lambda)?;
+        let quantity = quantity.to_scale(0)?.val.min(u64::MAX.into()) as u64;
+        Ok(quantity.saturating_add(reserve_units))
     }
 }
 
 
+/// Places the bid through Auction House's `auctioneer_buy`, which funds the buyer's
+/// escrow with `buyer_price` and creates the buyer trade state that execute sale matches.
 #[allow(clippy::too_many_arguments)]
 pub fn auctioneer_place_order_logic<'info>(
+    auction_house_program: Program<'info, AuctionHouseProgram>,
     wallet: Signer<'info>,
     payment_account: UncheckedAccount<'info>,
     transfer_authority: UncheckedAccount<'info>,
@@ -384,22 +970,20 @@ pub fn auctioneer_place_order_logic<'info>(
     token_account: Account<'info, TokenAccount>,
     metadata: UncheckedAccount<'info>,
     escrow_payment_account: UncheckedAccount<'info>,
-    auction_house: &mut Box<Account<'info, AuctionHouse>>,
+    auction_house: &Account<'info, AuctionHouse>,
     auction_house_fee_account: UncheckedAccount<'info>,
     buyer_trade_state: UncheckedAccount<'info>,
     authority: UncheckedAccount<'info>,
-    auctioneer_authority: Signer<'info>,
+    auctioneer_authority: UncheckedAccount<'info>,
     ah_auctioneer_pda: Account<'info, Auctioneer>,
     token_program: Program<'info, Token>,
     system_program: Program<'info, System>,
     rent: Sysvar<'info, Rent>,
     trade_state_bump: u8,
     escrow_payment_bump: u8,
+    auctioneer_authority_bump: u8,
     buyer_price: u64,
     token_size: u64,
-    public: bool,
-    escrow_canonical_bump: u8,
-    trade_state_canonical_bump: u8,
 ) -> Result<()>{
 
     if !auction_house.has_auctioneer {
@@ -408,168 +992,196 @@ pub fn auctioneer_place_order_logic<'info>(
 
     assert_valid_auctioneer(
         auction_house,
-        &auctioneer_authority.key,
+        auctioneer_authority.key,
         &ah_auctioneer_pda,
     )?;
-    if (escrow_canonical_bump != escrow_payment_bump)
-        || (trade_state_canonical_bump != trade_state_bump)
-    {
-        return Err(AuctionHouseError::BumpSeedNotInHashMap.into());
-    }
-
-    let auction_house_key = auction_house.key();
-    let seeds = [
-        PREFIX.as_bytes(),
-        auction_house_key.as_ref(),
-        FEE_PAYER.as_bytes(),
-        &[auction_house.fee_payer_bump],
-    ];
 
-    // makes a fee payer account depending on whether or not if the buyer (bidder)
-    // has signed to pay the fees or the auction house's authority has signed 
-    // to pay the fees, if the buyer signs it then the auction house authority will
-    // need to approve or sign to set the fee payer to the buyer.
-    // note that the above is true only if auction house's "requires_sign_off" field
-    // is set to true.
-    let (fee_payer, fee_seeds) = get_fee_payer(
-        &authority, // auction house authority
-        auction_house,
-        wallet.to_account_info(), //buyer or bidder's wallet
-        auction_house_fee_account.to_account_info(),
-        &seeds,
-    )?;
+    let cpi_program = auction_house_program.to_account_info();
+    let cpi_accounts = AHBuy {
+        wallet: wallet.to_account_info(),
+        payment_account: payment_account.to_account_info(),
+        transfer_authority: transfer_authority.to_account_info(),
+        treasury_mint: treasury_mint.to_account_info(),
+        token_account: token_account.to_account_info(),
+        metadata: metadata.to_account_info(),
+        escrow_payment_account: escrow_payment_account.to_account_info(),
+        authority: authority.to_account_info(),
+        auctioneer_authority: auctioneer_authority.to_account_info(),
+        auction_house: auction_house.to_account_info(),
+        auction_house_fee_account: auction_house_fee_account.to_account_info(),
+        buyer_trade_state: buyer_trade_state.to_account_info(),
+        ah_auctioneer_pda: ah_auctioneer_pda.to_account_info(),
+        token_program: token_program.to_account_info(),
+        system_program: system_program.to_account_info(),
+        rent: rent.to_account_info(),
+    };
 
-    let is_native = treasury_mint.key() == spl_token::native_mint::id();
+    auctioneer_buy_logic(
+        cpi_program,
+        cpi_accounts,
+        trade_state_bump,
+        escrow_payment_bump,
+        auctioneer_authority_bump,
+        buyer_price,
+        token_size,
+    )
+}
+
+/// Invokes Auction House's `auctioneer_buy`, which creates the buyer trade state for
+/// `buyer_price` and tops the buyer's escrow up to that price.
+#[inline(never)]
+pub(crate) fn auctioneer_buy_logic<'info>(
+    cpi_program: AccountInfo<'info>,
+    cpi_accounts: AHBuy<'info>,
+    trade_state_bump: u8,
+    escrow_payment_bump: u8,
+    auctioneer_authority_bump: u8,
+    buyer_price: u64,
+    token_size: u64,
+) -> Result<()> {
+    let buy_data = mpl_auction_house::instruction::AuctioneerBuy {
+        trade_state_bump,
+        escrow_payment_bump,
+        buyer_price,
+        token_size,
+    };
 
-    let auction_house_key = auction_house.key();
-    let wallet_key = wallet.key();
-    let escrow_signer_seeds = [
-        PREFIX.as_bytes(),
-        auction_house_key.as_ref(),
-        wallet_key.as_ref(),
-        &[escrow_payment_bump],
+    let ah_key = cpi_accounts.auction_house.key();
+    let auctioneer_seeds = [
+        AUCTIONEER.as_bytes(),
+        ah_key.as_ref(),
+        &[auctioneer_authority_bump],
     ];
 
-    //this will create the escrow_payment_account if it's not already created.
-    create_program_token_account_if_not_present(
-        &escrow_payment_account,
-        &system_program,
-        &fee_payer,
-        &token_program,
-        &treasury_mint,
-        &auction_house.to_account_info(),
-        &rent,
-        &escrow_signer_seeds,
-        fee_seeds,
-        is_native,
+    invoke_auction_house(
+        &cpi_program,
+        &cpi_accounts,
+        &[],
+        buy_data.data(),
+        &[&auctioneer_seeds],
     )?;
-    
-    if is_native {
-        assert_keys_equal(wallet.key(), payment_account.key())?;
-
-        if escrow_payment_account.lamports()
-            < buyer_price
-                .checked_add(rent.minimum_balance(escrow_payment_account.data_len()))
-                .ok_or(AuctionHouseError::NumericalOverflow)?
-        {
-            let diff = buyer_price
-                .checked_add(rent.minimum_balance(escrow_payment_account.data_len()))
-                .ok_or(AuctionHouseError::NumericalOverflow)?
-                .checked_sub(escrow_payment_account.lamports())
-                .ok_or(AuctionHouseError::NumericalOverflow)?;
-
-            invoke(
-                &system_instruction::transfer(
-                    &payment_account.key(),
-                    &escrow_payment_account.key(),
-                    diff,
-                ),
-                &[
-                    payment_account.to_account_info(),
-                    escrow_payment_account.to_account_info(),
-                    system_program.to_account_info(),
-                ],
-            )?;
-        }
-    } else {
-        let escrow_payment_loaded: spl_token::state::Account =
-            assert_initialized(&escrow_payment_account)?;
-
-        if escrow_payment_loaded.amount < buyer_price {
-            let diff = buyer_price
-                .checked_sub(escrow_payment_loaded.amount)
-                .ok_or(AuctionHouseError::NumericalOverflow)?;
-            invoke(
-                &spl_token::instruction::transfer(
-                    &token_program.key(),
-                    &payment_account.key(),
-                    &escrow_payment_account.key(),
-                    &transfer_authority.key(),
-                    &[],
-                    diff,
-                )?,
-                &[
-                    transfer_authority.to_account_info(),
-                    payment_account.to_account_info(),
-                    escrow_payment_account.to_account_info(),
-                    token_program.to_account_info(),
-                ],
-            )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // λ = 0.001 per second, a = 1.1, and the auction started at 0.
+    fn listing(mode: ListingMode, start_price: u64, reserve_price: u64, items_sold: u64) -> ListingConfig {
+        ListingConfig {
+            version: ListingConfig::VERSION,
+            token_size: 1_000,
+            items_sold,
+            start_price,
+            reserve_price,
+            decay_const: 36_000,
+            scale_factor: 11_000,
+            first_init_timestamp: 0,
+            end_timestamp: 86_400,
+            mode,
+            emission_rate: 1,
+            bump: 0,
+            listing_authority_bump: 0,
+            reserved: [0; LISTING_CONFIG_RESERVED],
         }
     }
-    assert_metadata_valid(&metadata, &token_account)?;
-    let ts_info = buyer_trade_state.to_account_info();
-    if ts_info.data_is_empty() {
-        let wallet_key = wallet.key();
-        let token_account_key = token_account.key();
-        if public {
-            create_or_allocate_account_raw(
-                crate::id(),
-                &ts_info,
-                &rent.to_account_info(),
-                &system_program,
-                &fee_payer,
-                TRADE_STATE_SIZE,
-                fee_seeds,
-                &[
-                    PREFIX.as_bytes(),
-                    wallet_key.as_ref(),
-                    auction_house_key.as_ref(),
-                    auction_house.treasury_mint.as_ref(),
-                    token_account.mint.as_ref(),
-                    &buyer_price.to_le_bytes(),
-                    &token_size.to_le_bytes(),
-                    &[trade_state_bump],
-                ],
-            )?;
-        } else {
-            create_or_allocate_account_raw(
-                crate::id(),
-                &ts_info,
-                &rent.to_account_info(),
-                &system_program,
-                &fee_payer,
-                TRADE_STATE_SIZE,
-                fee_seeds,
-                &[
-                    PREFIX.as_bytes(),
-                    wallet_key.as_ref(),
-                    auction_house_key.as_ref(),
-                    token_account_key.as_ref(),
-                    auction_house.treasury_mint.as_ref(),
-                    token_account.mint.as_ref(),
-                    &buyer_price.to_le_bytes(),
-                    &token_size.to_le_bytes(),
-                    &[trade_state_bump],
-                ],
-            )?;
+
+    // Reference prices are the pricing formulas evaluated at 60 significant digits.
+    fn assert_price(listing: &ListingConfig, order_size: u64, now: UnixTimestamp, expected: u64) {
+        let price = listing.calculate_price_at(order_size, now).unwrap();
+        assert!(price.abs_diff(expected) <= 1, "{} is not within 1 of {}", price, expected);
+    }
+
+    #[test]
+    fn discrete_price_without_reserve() {
+        // 1e9 * 1.1^2 * (1.1^3 - 1) / (0.1 * e^0.6) = 2,198,045,483.72
+        let listing = listing(ListingMode::Discrete, 1_000_000_000, 0, 2);
+        assert_price(&listing, 3, 600, 2_198_045_484);
+    }
+
+    #[test]
+    fn discrete_price_clamps_units_below_reserve() {
+        // Unit prices are 664.1M, 730.5M and 803.5M, so only the first is raised to the reserve.
+        let listing = listing(ListingMode::Discrete, 1_000_000_000, 700_000_000, 2);
+        assert_price(&listing, 3, 600, 2_233_983_405);
+    }
+
+    #[test]
+    fn continuous_price_without_reserve() {
+        // T = 600 - 100 = 500: 1e6 * (e^0.05 - 1) / (0.001 * e^0.5) = 31,097,491.91
+        let listing = listing(ListingMode::Continuous, 1_000_000, 0, 100);
+        assert_price(&listing, 50, 600, 31_097_492);
+    }
+
+    #[test]
+    fn continuous_price_clamps_auctions_below_reserve() {
+        // Auctions older than ln(1e6 / 6e5) / 0.001 = 510.8s are below the reserve. T = 550, so
+        // the 40 oldest are clamped and the curve prices the other 10 from age 510.
+        let listing = listing(ListingMode::Continuous, 1_000_000, 600_000, 50);
+        assert_price(&listing, 50, 600, 30_035_081);
+    }
+
+    #[test]
+    fn continuous_price_needs_emitted_units() {
+        let listing = listing(ListingMode::Continuous, 1_000_000, 0, 100);
+        assert!(listing.calculate_price_at(501, 600).is_err());
+    }
+
+    // The largest order whose price fits in `budget`, found by pricing every size in turn.
+    fn brute_force_quantity(listing: &ListingConfig, budget: u64, now: UnixTimestamp) -> u64 {
+        (0..=listing.available_supply(now))
+            .take_while(|&order_size| listing.fits_budget(order_size, budget, now))
+            .last()
+            .unwrap()
+    }
+
+    fn assert_quantity_for_budget(listing: &ListingConfig, budgets: &[u64], now: UnixTimestamp) {
+        for &budget in budgets {
+            assert_eq!(
+                listing.max_quantity_for_budget(budget, now).unwrap(),
+                brute_force_quantity(listing, budget, now),
+                "budget {}",
+                budget
+            );
         }
-        sol_memset(
-            *ts_info.try_borrow_mut_data()?,
-            trade_state_bump,
-            TRADE_STATE_SIZE,
-        );
     }
-    // Allow The same bid to be sent with no issues
-    Ok(())
-}
\ No newline at end of file
+
+    #[test]
+    fn discrete_quantity_for_budget() {
+        // The curve starts at 664.1M per unit: 2,198,045,484 buys exactly 3 units.
+        let listing = listing(ListingMode::Discrete, 1_000_000_000, 0, 2);
+        assert_eq!(listing.max_quantity_for_budget(2_198_045_484, 600).unwrap(), 3);
+        assert_eq!(listing.max_quantity_for_budget(2_198_045_483, 600).unwrap(), 2);
+        assert_quantity_for_budget(&listing, &[0, 664_000_000, 5_000_000_000, 123_456_789_012], 600);
+    }
+
+    #[test]
+    fn discrete_quantity_for_budget_with_binding_reserve() {
+        // After an hour the curve starts at 1e9 * 1.21 * e^-3.6 = 33.1M, so the next 32 units
+        // are clamped to the 700M reserve. Ignoring the reserve, 7e9 would buy 32 units.
+        let listing = listing(ListingMode::Discrete, 1_000_000_000, 700_000_000, 2);
+        assert_eq!(listing.max_quantity_for_budget(7_000_000_000, 3_600).unwrap(), 10);
+        assert_quantity_for_budget(&listing, &[699_999_999, 22_400_000_000, 30_000_000_000], 3_600);
+    }
+
+    #[test]
+    fn continuous_quantity_for_budget() {
+        // 31,097,492 buys exactly the 50 auctions priced in continuous_price_without_reserve.
+        let listing = listing(ListingMode::Continuous, 1_000_000, 0, 100);
+        assert_eq!(listing.max_quantity_for_budget(31_097_492, 600).unwrap(), 50);
+        assert_eq!(listing.max_quantity_for_budget(31_097_491, 600).unwrap(), 49);
+        assert_quantity_for_budget(&listing, &[0, 1_000_000, 100_000_000, u64::MAX], 600);
+    }
+
+    #[test]
+    fn continuous_quantity_for_budget_with_binding_reserve() {
+        // At 1200s the oldest auction is 1150s old and worth 1e6 * e^-1.15 = 316.6K, so the
+        // 640 auctions older than 510.8s are clamped to the 600K reserve. Ignoring the reserve,
+        // 60M would buy 173 auctions.
+        let listing = listing(ListingMode::Continuous, 1_000_000, 600_000, 50);
+        assert_eq!(listing.max_quantity_for_budget(60_000_000, 1_200).unwrap(), 100);
+        assert_quantity_for_budget(&listing, &[599_999, 384_000_000, 500_000_000], 1_200);
+    }
+}
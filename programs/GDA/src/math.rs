@@ -1,5 +1,81 @@
 
 use anchor_lang::prelude::*;
+
+/// Working scale of `exp` and `exp_neg`.
+pub const EXP_SCALE: u8 = 18;
+/// ln(2) at `EXP_SCALE`.
+const LN_2: u128 = 693_147_180_559_945_309;
+
+fn pow10(exponent: u8) -> Result<u128> {
+    Ok(10u128
+        .checked_pow(exponent.into())
+        .ok_or(MathError::Overflow)?)
+}
+
+fn round_div(numerator: u128, denominator: u128) -> u128 {
+    numerator / denominator + u128::from(numerator % denominator >= denominator - denominator / 2)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Rounding {
+    Down,
+    Up,
+}
+
+// Full 256-bit product of two u128, as (high, low) halves.
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+    let low = a_lo * b_lo;
+    let cross_a = a_lo * b_hi;
+    let cross_b = a_hi * b_lo;
+    let mid = (low >> 64) + (cross_a & MASK) + (cross_b & MASK);
+    (
+        a_hi * b_hi + (cross_a >> 64) + (cross_b >> 64) + (mid >> 64),
+        (low & MASK) | (mid << 64),
+    )
+}
+
+// a * b / denominator with a 256-bit intermediate product, so only a quotient that does not
+// fit in u128 overflows. Products that fit in u128 skip the long division.
+fn mul_div(a: u128, b: u128, denominator: u128, rounding: Rounding) -> Result<u128> {
+    if denominator == 0 {
+        return err!(MathError::DivisionByZero);
+    }
+    let (high, low) = match a.checked_mul(b) {
+        Some(product) => (0, product),
+        None => widening_mul(a, b),
+    };
+    let (quotient, remainder) = if high == 0 {
+        (low / denominator, low % denominator)
+    } else {
+        if high >= denominator {
+            return err!(MathError::Overflow);
+        }
+        // Shift-subtract division of (high, low); the remainder stays below the denominator,
+        // with the bit shifted out of it accounting for values between 2^128 and 2 * denominator.
+        let mut remainder = high;
+        let mut quotient = 0u128;
+        for bit in (0..128).rev() {
+            let carry = remainder >> 127;
+            remainder = (remainder << 1) | ((low >> bit) & 1);
+            quotient <<= 1;
+            if carry == 1 || remainder >= denominator {
+                remainder = remainder.wrapping_sub(denominator);
+                quotient |= 1;
+            }
+        }
+        (quotient, remainder)
+    };
+    let round_up = match rounding {
+        Rounding::Down => false,
+        Rounding::Up => remainder > 0,
+    };
+    Ok(quotient
+        .checked_add(round_up.into())
+        .ok_or(MathError::Overflow)?)
+}
 #[derive(Debug, Copy,Clone)]
 pub struct Decimal {
     pub val: u128,
@@ -25,187 +101,264 @@ impl Decimal {
         }
     }
     
-    pub fn to_u64(self) -> u64 {
-        self.val.try_into().unwrap()
+    pub fn to_u64(self) -> Result<u64> {
+        Ok(self.val.try_into().map_err(|_| MathError::Overflow)?)
     }
     
-    pub fn to_scale(self, scale: u8) -> Self {
-        Self {
+    pub fn to_scale(self, scale: u8) -> Result<Self> {
+        Ok(Self {
             val: if self.scale > scale {
                 self.val
-                    .checked_div(10u128.pow((self.scale - scale).into()))
-                    .unwrap()
+                    .checked_div(pow10(self.scale - scale)?)
+                    .ok_or(MathError::DivisionByZero)?
             } else {
                 self.val
-                    .checked_mul(10u128.pow((scale - self.scale).into()))
-                    .unwrap()
+                    .checked_mul(pow10(scale - self.scale)?)
+                    .ok_or(MathError::Overflow)?
             },
             scale,
+        })
+    }
+    /// e^self, returned at the scale of `self`.
+    ///
+    /// The exponent is reduced to x = k*ln(2) + r with 0 <= r < ln(2), e^r is summed from its
+    /// Taylor series at `EXP_SCALE` and the result is shifted by 2^k. The error is below
+    /// e^self * 10^-16 plus half a unit in the last place of `self.scale`.
+    ///
+    /// Fails with `ExponentOutOfRange` when the result does not fit in a u128 at `self.scale`
+    /// (e.g. above ~54.18 at scale 15) or when `self.scale` exceeds `EXP_SCALE`.
+    pub fn exp(self) -> Result<Self> {
+        let (series, k) = self.exp_reduced(false)?;
+        let denominator = 10u128.pow((EXP_SCALE - self.scale).into());
+        let headroom = series.leading_zeros().saturating_sub(1);
+        let val = if k <= headroom {
+            round_div(series << k, denominator)
+        } else {
+            let shifted = round_div(series << headroom, denominator);
+            if shifted.leading_zeros() < k - headroom {
+                return err!(MathError::ExponentOutOfRange);
+            }
+            shifted << (k - headroom)
+        };
+        Ok(Self {
+            val,
+            scale: self.scale,
+        })
+    }
+
+    /// e^-self, returned at the scale of `self`, with the same reduction and error bound as
+    /// `exp`. Large exponents round down to zero instead of failing, so a price that has
+    /// decayed for days evaluates to zero rather than overflowing.
+    pub fn exp_neg(self) -> Result<Self> {
+        let (series, k) = self.exp_reduced(true)?;
+        let denominator = 10u128.pow((EXP_SCALE - self.scale).into());
+        let val = if denominator.leading_zeros() > k {
+            round_div(series, denominator << k)
+        } else {
+            0
+        };
+        Ok(Self {
+            val,
+            scale: self.scale,
+        })
+    }
+
+    /// e^(self - other) for two non-negative exponents of the same scale, through `exp` or
+    /// `exp_neg` depending on the sign of the difference. Lets a growth term and a decay term
+    /// be combined before exponentiating, so neither has to fit on its own.
+    pub fn exp_diff(self, other: Decimal) -> Result<Self> {
+        require!(self.scale == other.scale, MathError::ScaleMismatch);
+        if self.val >= other.val {
+            self.sub(other)?.exp()
+        } else {
+            other.sub(self)?.exp_neg()
+        }
+    }
+
+    /// Natural logarithm, returned at the scale of `self`. Only defined for `self >= 1`, where
+    /// the result is non-negative; smaller inputs fail with `LogarithmOutOfRange`.
+    ///
+    /// The input is reduced to x = 2^k * y with 1 <= y < 2 and ln(y) = 2 * atanh((y - 1) / (y + 1))
+    /// is summed at `EXP_SCALE`. The error is below 10^-16 plus half a unit in the last place
+    /// of `self.scale`.
+    pub fn ln(self) -> Result<Self> {
+        if self.scale > EXP_SCALE || self.val < self.denominator() {
+            return err!(MathError::LogarithmOutOfRange);
+        }
+        let one = 10u128.pow(EXP_SCALE.into());
+
+        // Drop low bits of very large inputs so that rescaling to EXP_SCALE cannot overflow.
+        let excess_bits = 64u32.saturating_sub(self.val.leading_zeros());
+        let mut k = excess_bits;
+        let mut y = (self.val >> excess_bits) * 10u128.pow((EXP_SCALE - self.scale).into());
+        while y >= 2 * one {
+            y >>= 1;
+            k += 1;
+        }
+
+        // z < 1/3, so every term is at least 9 times smaller than the previous one.
+        let z = (y - one) * one / (y + one);
+        let z_squared = z * z / one;
+        let mut series = z;
+        let mut power = z;
+        let mut n: u128 = 3;
+        while power > 0 {
+            power = power * z_squared / one;
+            series += power / n;
+            n += 2;
         }
+
+        let val = u128::from(k) * LN_2 + 2 * series;
+        Ok(Self {
+            val: round_div(val, 10u128.pow((EXP_SCALE - self.scale).into())),
+            scale: self.scale,
+        })
+    }
+
+    // Returns e^r at EXP_SCALE and k such that e^(±self) = e^r * 2^(±k).
+    fn exp_reduced(self, negative: bool) -> Result<(u128, u32)> {
+        if self.scale > EXP_SCALE {
+            return err!(MathError::ExponentOutOfRange);
+        }
+        let one = 10u128.pow(EXP_SCALE.into());
+        let x = self
+            .val
+            .checked_mul(10u128.pow((EXP_SCALE - self.scale).into()))
+            .ok_or(MathError::ExponentOutOfRange)?;
+
+        // Positive exponents round k down and negative ones round it up, so r is never negative.
+        let mut k = x / LN_2;
+        let r = if negative {
+            if x % LN_2 != 0 {
+                k += 1;
+            }
+            k * LN_2 - x
+        } else {
+            x - k * LN_2
+        };
+        let k: u32 = k.try_into().map_err(|_| MathError::ExponentOutOfRange)?;
+
+        // r < ln(2), so the terms fall below one unit at EXP_SCALE after about 20 steps.
+        let mut series = one;
+        let mut term = one;
+        let mut n: u128 = 1;
+        while term > 0 {
+            term = term * r / one / n;
+            series += term;
+            n += 1;
+        }
+        Ok((series, k))
     }
-    pub fn to_scale_up(self, scale: u8) -> Self {
+    pub fn to_scale_up(self, scale: u8) -> Result<Self> {
         let decimal = Self::new(self.val, scale);
         if self.scale >= scale {
-            decimal.div_up(Self::new(
-                10u128.pow((self.scale - scale).try_into().unwrap()),
-                0,
-            ))
+            decimal.div_up(Self::new(pow10(self.scale - scale)?, 0))
         } else {
-            decimal.mul_up(Self::new(
-                10u128.pow((scale - self.scale).try_into().unwrap()),
-                0,
-            ))
+            decimal.mul_up(Self::new(pow10(scale - self.scale)?, 0))
         }
     }
 }
 
 impl Mul<Decimal> for Decimal {
-    fn mul(self, value: Decimal) -> Self {
-        Self {
-            val: self
-                .val
-                .checked_mul(value.val)
-                .unwrap()
-                .checked_div(value.denominator())
-                .unwrap(),
+    fn mul(self, value: Decimal) -> Result<Self> {
+        Ok(Self {
+            val: mul_div(self.val, value.val, value.denominator(), Rounding::Down)?,
             scale: self.scale,
-        }
+        })
     }
 }
 impl Mul<u128> for Decimal {
-    fn mul(self, value: u128) -> Self {
-        Self {
-            val: self.val.checked_mul(value).unwrap(),
+    fn mul(self, value: u128) -> Result<Self> {
+        Ok(Self {
+            val: self.val.checked_mul(value).ok_or(MathError::Overflow)?,
             scale: self.scale,
-        }
+        })
     }
 }
 impl MulUp<Decimal> for Decimal {
-    fn mul_up(self, other: Decimal) -> Self {
-        let denominator = other.denominator();
-
-        Self {
-            val: self
-                .val
-                .checked_mul(other.val)
-                .unwrap()
-                .checked_add(denominator.checked_sub(1).unwrap())
-                .unwrap()
-                .checked_div(denominator)
-                .unwrap(),
+    fn mul_up(self, other: Decimal) -> Result<Self> {
+        Ok(Self {
+            val: mul_div(self.val, other.val, other.denominator(), Rounding::Up)?,
             scale: self.scale,
-        }
+        })
     }
 }
 impl Add<Decimal> for Decimal {
     fn add(self, value: Decimal) -> Result<Self> {
-        require!(self.scale == value.scale, MyError::DifferentScale);
+        require!(self.scale == value.scale, MathError::ScaleMismatch);
 
         Ok(Self {
-            val: self.val.checked_add(value.val).unwrap(),
+            val: self.val.checked_add(value.val).ok_or(MathError::Overflow)?,
             scale: self.scale,
         })
     }
 }
 impl Sub<Decimal> for Decimal {
     fn sub(self, value: Decimal) -> Result<Self> {
-        require!(self.scale == value.scale, MyError::DifferentScale);
+        require!(self.scale == value.scale, MathError::ScaleMismatch);
         Ok(Self {
-            val: self.val.checked_sub(value.val).unwrap(),
+            val: self.val.checked_sub(value.val).ok_or(MathError::Underflow)?,
             scale: self.scale,
         })
     }
 }
 impl Div<Decimal> for Decimal {
-    fn div(self, other: Decimal) -> Self {
-        Self {
-            val: self
-                .val
-                .checked_mul(other.denominator())
-                .unwrap()
-                .checked_div(other.val)
-                .unwrap(),
+    fn div(self, other: Decimal) -> Result<Self> {
+        Ok(Self {
+            val: mul_div(self.val, other.denominator(), other.val, Rounding::Down)?,
             scale: self.scale,
-        }
+        })
     }
 }
 impl DivUp<Decimal> for Decimal {
-    fn div_up(self, other: Decimal) -> Self {
-        Self {
-            val: self
-                .val
-                .checked_mul(other.denominator())
-                .unwrap()
-                .checked_add(other.val.checked_sub(1).unwrap())
-                .unwrap()
-                .checked_div(other.val)
-                .unwrap(),
+    fn div_up(self, other: Decimal) -> Result<Self> {
+        Ok(Self {
+            val: mul_div(self.val, other.denominator(), other.val, Rounding::Up)?,
             scale: self.scale,
-        }
+        })
     }
 }
 impl DivScale<Decimal> for Decimal {
-    fn div_to_scale(self, other: Decimal, to_scale: u8) -> Self {
-        let decimal_difference = (self.scale as i32)
-            .checked_sub(to_scale.into())
-            .unwrap()
-            .checked_sub(other.scale.into())
-            .unwrap();
+    fn div_to_scale(self, other: Decimal, to_scale: u8) -> Result<Self> {
+        let decimal_difference = i32::from(self.scale) - i32::from(to_scale) - i32::from(other.scale);
+        let shift = pow10(
+            decimal_difference
+                .unsigned_abs()
+                .try_into()
+                .map_err(|_| MathError::Overflow)?,
+        )?;
 
         let val = if decimal_difference > 0 {
             self.val
                 .checked_div(other.val)
-                .unwrap()
-                .checked_div(10u128.pow(decimal_difference.try_into().unwrap()))
-                .unwrap()
+                .ok_or(MathError::DivisionByZero)?
+                / shift
         } else {
             self.val
-                .checked_mul(10u128.pow((-decimal_difference).try_into().unwrap()))
-                .unwrap()
+                .checked_mul(shift)
+                .ok_or(MathError::Overflow)?
                 .checked_div(other.val)
-                .unwrap()
+                .ok_or(MathError::DivisionByZero)?
         };
-        Self {
+        Ok(Self {
             val,
             scale: to_scale,
-        }
+        })
     }
 }
-impl PowAccuracy<u128> for Decimal {
-    fn pow_with_accuracy(self, exp: u128) -> Self {
-        let one = Decimal {
-            val: self.denominator(),
-            scale: self.scale,
-        };
-        if exp == 0 {
-            return one;
-        }
-        let mut current_exp = exp;
-        let mut base = self;
-        let mut result = one;
+impl TryFrom<Decimal> for u64 {
+    type Error = anchor_lang::error::Error;
 
-        while current_exp > 0 {
-            if current_exp % 2 != 0 {
-                result = result.mul(base);
-            }
-            current_exp /= 2;
-            base = base.mul(base);
-        }
-        return result;
-    }
-}
-impl Into<u64> for Decimal {
-    fn into(self) -> u64 {
-        self.val.try_into().unwrap()
+    fn try_from(decimal: Decimal) -> Result<u64> {
+        decimal.to_u64()
     }
 }
-impl Into<u128> for Decimal {
-    fn into(self) -> u128 {
-        self.val.try_into().unwrap()
+impl From<Decimal> for u128 {
+    fn from(decimal: Decimal) -> u128 {
+        decimal.val
     }
 }
 
-
 pub trait Sub<T>: Sized {
     fn sub(self, rhs: T) -> Result<Self>;
 }
@@ -213,28 +366,145 @@ pub trait Add<T>: Sized {
     fn add(self, rhs: T) -> Result<Self>;
 }
 pub trait Div<T>: Sized {
-    fn div(self, rhs: T) -> Self;
+    fn div(self, rhs: T) -> Result<Self>;
 }
-pub trait DivScale<T> {
-    fn div_to_scale(self, rhs: T, to_scale: u8) -> Self;
+pub trait DivScale<T>: Sized {
+    fn div_to_scale(self, rhs: T, to_scale: u8) -> Result<Self>;
 }
 pub trait DivUp<T>: Sized {
-    fn div_up(self, rhs: T) -> Self;
+    fn div_up(self, rhs: T) -> Result<Self>;
 }
 pub trait Mul<T>: Sized {
-    fn mul(self, rhs: T) -> Self;
+    fn mul(self, rhs: T) -> Result<Self>;
 }
 pub trait MulUp<T>: Sized {
-    fn mul_up(self, rhs: T) -> Self;
-}
-pub trait PowAccuracy<T>: Sized {
-    fn pow_with_accuracy(self, rhs: T) -> Self;
+    fn mul_up(self, rhs: T) -> Result<Self>;
 }
 
-#[error_code]
-pub enum MyError{
-    #[msg("Invalid")]
-    DifferentScale,
+/// Errors raised by `Decimal` arithmetic. Numbered from 6100 so they stay apart from
+/// `AuctioneerError`.
+#[error_code(offset = 6100)]
+pub enum MathError {
+    // 6100
+    #[msg("Arithmetic overflow")]
+    Overflow,
+
+    // 6101
+    #[msg("Arithmetic underflow")]
+    Underflow,
+
+    // 6102
+    #[msg("Division by zero")]
+    DivisionByZero,
+
+    // 6103
+    #[msg("Decimals must share the same scale")]
+    ScaleMismatch,
+
+    // 6104
+    #[msg("Exponent is out of range")]
+    ExponentOutOfRange,
+
+    // 6105
+    #[msg("Logarithm is only defined for values of at least one")]
+    LogarithmOutOfRange,
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALE: u8 = 15;
+
+    fn decimal(val: u128) -> Decimal {
+        Decimal::new(val, SCALE)
+    }
+
+    // Reference values are e^x and ln(x) at 60 significant digits, rounded to `SCALE`.
+    fn assert_close(actual: u128, expected: u128) {
+        let tolerance = expected / 10u128.pow(16) + 1;
+        assert!(
+            actual.abs_diff(expected) <= tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn exp_matches_reference_values() {
+        let cases = [
+            (0, 1_000_000_000_000_000),
+            (500_000_000_000_000, 1_648_721_270_700_128),
+            (1_000_000_000_000_000, 2_718_281_828_459_045),
+            (10_000_000_000_000_000, 22_026_465_794_806_716_517),
+            (40_000_000_000_000_000, 235_385_266_837_019_985_407_899_910_749_035),
+            (53_900_000_000_000_000, 256_137_151_003_183_765_413_539_190_133_033_738_344),
+        ];
+        for (x, expected) in cases {
+            assert_close(decimal(x).exp().unwrap().val, expected);
+        }
+    }
+
+    #[test]
+    fn exp_fails_past_the_overflow_edge() {
+        assert!(decimal(54_184_062_716_762_314).exp().is_ok());
+        assert!(decimal(54_200_000_000_000_000).exp().is_err());
+        assert!(decimal(u128::MAX).exp().is_err());
+    }
+
+    #[test]
+    fn exp_neg_matches_reference_values() {
+        assert_close(decimal(1_000_000_000_000_000).exp_neg().unwrap().val, 367_879_441_171_442);
+        assert_close(decimal(10_000_000_000_000_000).exp_neg().unwrap().val, 45_399_929_762);
+        assert_close(decimal(30_000_000_000_000_000).exp_neg().unwrap().val, 94);
+    }
+
+    #[test]
+    fn exp_neg_underflows_to_zero() {
+        assert_eq!(decimal(50_000_000_000_000_000).exp_neg().unwrap().val, 0);
+        assert_eq!(decimal(1_000_000_000_000_000_000_000).exp_neg().unwrap().val, 0);
+    }
+
+    #[test]
+    fn ln_matches_reference_values() {
+        assert_eq!(decimal(1_000_000_000_000_000).ln().unwrap().val, 0);
+        assert_close(decimal(2_000_000_000_000_000).ln().unwrap().val, 693_147_180_559_945);
+        assert_close(decimal(10_000_000_000_000_000).ln().unwrap().val, 2_302_585_092_994_046);
+    }
+
+    #[test]
+    fn ln_handles_very_large_inputs() {
+        assert_close(decimal(u128::MAX).ln().unwrap().val, 54_184_062_716_762_314);
+        assert_eq!(Decimal::new(u128::MAX, 0).ln().unwrap().val, 89);
+    }
+
+    #[test]
+    fn ln_fails_below_one() {
+        assert!(decimal(999_999_999_999_999).ln().is_err());
+    }
 
+    #[test]
+    fn mul_div_handles_products_above_u128() {
+        assert_eq!(
+            mul_div(u128::MAX, u128::MAX, u128::MAX, Rounding::Down).unwrap(),
+            u128::MAX
+        );
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 90, Rounding::Down).unwrap(), 1 << 110);
+        assert_eq!(
+            mul_div(u128::MAX, 3, 6, Rounding::Down).unwrap(),
+            (1 << 127) - 1
+        );
+        assert_eq!(mul_div(u128::MAX, 3, 6, Rounding::Up).unwrap(), 1 << 127);
+        assert_eq!(mul_div(1 << 127, 6, 4, Rounding::Down).unwrap(), 3 << 126);
+    }
+
+    #[test]
+    fn mul_div_fails_when_the_quotient_overflows() {
+        assert!(mul_div(1 << 127, 8, 4, Rounding::Down).is_err());
+        assert!(mul_div(u128::MAX, u128::MAX, 1, Rounding::Down).is_err());
+        assert!(mul_div(u128::MAX, 1, 1, Rounding::Up).is_ok());
+        assert!(mul_div(1, 1, 0, Rounding::Down).is_err());
+    }
+}
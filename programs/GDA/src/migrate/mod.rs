@@ -0,0 +1,107 @@
+use crate::*;
+use anchor_lang::{system_program, Discriminator};
+
+#[derive(Accounts)]
+pub struct MigrateListingConfig<'info> {
+    /// CHECK: Owned by this program; the discriminator and layout are checked in
+    /// migrate_listing_config since older layouts do not deserialize as `ListingConfig`.
+    #[account(mut, owner = crate::ID)]
+    pub listing_config: UncheckedAccount<'info>,
+
+    /// Pays the rent for the extra space.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// `ListingConfig` as written before the layout was versioned: no version byte, integer λ per
+/// second and alpha, discrete pricing only, and no reserve price or listing authority.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ListingConfigV0 {
+    pub token_size: u64,
+    pub items_sold: u64,
+    pub start_price: u64,
+    pub decay_const: u8,
+    pub scale_factor: u64,
+    pub first_init_timestamp: UnixTimestamp,
+    pub end_timestamp: UnixTimestamp,
+    pub bump: u8,
+}
+
+impl ListingConfigV0 {
+    /// Converts to the current layout. λ and alpha were whole numbers, so they convert to
+    /// basis points exactly; `listing_authority_bump` is the bump of `listing_config`'s
+    /// listing authority PDA.
+    fn upgrade(self, listing_authority_bump: u8) -> Result<ListingConfig> {
+        Ok(ListingConfig {
+            version: ListingConfig::VERSION,
+            token_size: self.token_size,
+            items_sold: self.items_sold,
+            start_price: self.start_price,
+            reserve_price: 0,
+            decay_const: u64::from(self.decay_const)
+                .checked_mul(MAX_DECAY_CONSTANT)
+                .ok_or(AuctionHouseError::NumericalOverflow)?,
+            scale_factor: self
+                .scale_factor
+                .checked_mul(BASIS_POINTS)
+                .ok_or(AuctionHouseError::NumericalOverflow)?,
+            first_init_timestamp: self.first_init_timestamp,
+            end_timestamp: self.end_timestamp,
+            mode: ListingMode::Discrete,
+            emission_rate: 0,
+            bump: self.bump,
+            listing_authority_bump,
+            reserved: [0; LISTING_CONFIG_RESERVED],
+        })
+    }
+}
+
+/// Upgrades a `ListingConfig` to the current layout in place. Accounts shorter than
+/// `ListingConfig::LEN` hold the unversioned layout: they are read as `ListingConfigV0`, grown
+/// from their old size to `ListingConfig::LEN` with the payer topping up rent, and rewritten
+/// at `ListingConfig::VERSION`. Later versions take their new fields out of `reserved`, so
+/// current-size accounts only have `version` checked and are otherwise left untouched.
+pub fn migrate_listing_config(ctx: Context<MigrateListingConfig>) -> Result<()> {
+    let listing_config = ctx.accounts.listing_config.to_account_info();
+    let legacy = {
+        let data = listing_config.try_borrow_data()?;
+        if data.len() < 8 || data[..8] != ListingConfig::discriminator() {
+            return err!(ErrorCode::AccountDiscriminatorMismatch);
+        }
+        if data.len() >= ListingConfig::LEN {
+            let current = ListingConfig::try_deserialize(&mut &data[..])?;
+            if current.version > ListingConfig::VERSION {
+                return err!(AuctioneerError::UnsupportedListingVersion);
+            }
+            return Ok(());
+        }
+        ListingConfigV0::deserialize(&mut &data[8..])?
+    };
+    let (_, listing_authority_bump) = Pubkey::find_program_address(
+        &[LISTING_AUTHORITY.as_bytes(), listing_config.key.as_ref()],
+        &crate::ID,
+    );
+    let upgraded = legacy.upgrade(listing_authority_bump)?;
+
+    let required_lamports = Rent::get()?.minimum_balance(ListingConfig::LEN);
+    let top_up = required_lamports.saturating_sub(listing_config.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: listing_config.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    listing_config.realloc(ListingConfig::LEN, true)?;
+
+    let mut data = listing_config.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    upgraded.try_serialize(&mut writer)
+}
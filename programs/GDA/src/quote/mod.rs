@@ -0,0 +1,51 @@
+use crate::{util::*, *};
+use anchor_lang::solana_program::program::set_return_data;
+
+#[derive(Accounts)]
+pub struct Quote<'info> {
+    /// The Listing Config being priced.
+    pub listing_config: Account<'info, ListingConfig>,
+}
+
+/// Price of `order_size` units as of the current slot, published through `set_return_data`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceQuote {
+    /// Total price of the order, the amount `buy` and `place_order` charge for it.
+    pub cumulative_price: u64,
+    /// Price of the last unit of the order, i.e. what one more unit on top of the first
+    /// `order_size - 1` would cost.
+    pub marginal_price: u64,
+    /// Units that can still be bought right now.
+    pub available_supply: u64,
+}
+
+/// Prices `order_size` units with the same `calculate_price_at` used at settlement and returns a
+/// borsh-encoded `PriceQuote` as return data. Nothing is written, so clients get exact quotes
+/// by simulating the transaction.
+pub fn quote(ctx: Context<Quote>, order_size: u64) -> Result<()> {
+    let listing_config = &ctx.accounts.listing_config;
+    assert_auction_active(listing_config)?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let available_supply = listing_config.available_supply(now);
+    if order_size > available_supply {
+        return err!(AuctioneerError::InsufficientEmission);
+    }
+    let cumulative_price = listing_config.calculate_price_at(order_size, now)?;
+    let marginal_price = if order_size == 0 {
+        0
+    } else {
+        cumulative_price
+            .checked_sub(listing_config.calculate_price_at(order_size - 1, now)?)
+            .ok_or(MathError::Underflow)?
+    };
+
+    let price_quote = PriceQuote {
+        cumulative_price,
+        marginal_price,
+        available_supply,
+    };
+    set_return_data(&price_quote.try_to_vec()?);
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use crate::{util::*, AuctionHouse, *};
+
+#[derive(Accounts)]
+#[instruction(token_size: u64)]
+pub struct UpdateListing<'info> {
+    /// Auction House Program
+    pub auction_house_program: Program<'info, AuctionHouseProgram>,
+
+    /// The Listing Config being updated.
+    #[account(
+        mut,
+        seeds=[
+            LISTING_CONFIG.as_bytes(),
+            wallet.key().as_ref(),
+            auction_house.key().as_ref(),
+            auction_house.treasury_mint.as_ref(),
+            token_mint.key().as_ref(),
+            &token_size.to_le_bytes()
+        ],
+        bump = listing_config.bump,
+    )]
+    pub listing_config: Account<'info, ListingConfig>,
+
+    /// Seller wallet account.
+    pub wallet: Signer<'info>,
+
+    /// Token mint account of the listed tokens.
+    pub token_mint: Box<Account<'info, Mint>>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump=auction_house.bump,
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+}
+
+/// Replaces the curve parameters and end time of a listing, validated as in `auctioneer_sell`.
+/// Until the first sale everything can change. Afterwards buyers have paid along the current
+/// curve, so it is locked and the end time can only be pushed back.
+pub fn update_listing(
+    ctx: Context<UpdateListing>,
+    _token_size: u64,
+    start_price: u64,
+    reserve_price: u64,
+    decay_constant: u64,
+    scale_factor: u64,
+    end_time: UnixTimestamp,
+) -> Result<()> {
+    let listing_config = &mut ctx.accounts.listing_config;
+    assert_valid_curve(
+        start_price,
+        reserve_price,
+        decay_constant,
+        scale_factor,
+        listing_config.mode,
+        listing_config.emission_rate,
+    )?;
+    assert_end_time_in_future(end_time, Clock::get()?.unix_timestamp)?;
+    if listing_config.first_init_timestamp >= end_time {
+        return err!(AuctioneerError::InvalidAuctionWindow);
+    }
+
+    if listing_config.items_sold > 0 {
+        if start_price != listing_config.start_price
+            || reserve_price != listing_config.reserve_price
+            || decay_constant != listing_config.decay_const
+            || scale_factor != listing_config.scale_factor
+        {
+            return err!(AuctioneerError::CurveLocked);
+        }
+        if end_time < listing_config.end_timestamp {
+            return err!(AuctioneerError::CannotShortenAuction);
+        }
+    }
+
+    listing_config.start_price = start_price;
+    listing_config.reserve_price = reserve_price;
+    listing_config.decay_const = decay_constant;
+    listing_config.scale_factor = scale_factor;
+    listing_config.end_timestamp = end_time;
+
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use anchor_lang::{
     prelude::*,
     solana_program::{
-       // program::invoke_signed,
+        clock::UnixTimestamp,
+        instruction::Instruction,
+        program::invoke_signed,
         program_memory::sol_memcmp,
        // program_option::COption,
       //  program_pack::{IsInitialized, Pack},
@@ -10,7 +12,15 @@ use anchor_lang::{
     },
 };
 
-use crate::{errors::*, ListingConfig, AuctionHouse,  Auctioneer,AuctionHouseError };
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address},
+    token::TokenAccount,
+};
+
+use crate::{
+    errors::*, ListingConfig, ListingMode, AuctionHouse, Auctioneer, AuctionHouseError,
+    BASIS_POINTS, MAX_DECAY_CONSTANT,
+};
 
 pub fn assert_auction_active(listing_config: &Account<ListingConfig>) -> Result<()> {
     let clock = Clock::get()?;
@@ -18,13 +28,75 @@ pub fn assert_auction_active(listing_config: &Account<ListingConfig>) -> Result<
 
     if current_timestamp < listing_config.first_init_timestamp {
         return err!(AuctioneerError::AuctionNotStarted);
-    } else if current_timestamp > listing_config.end_timestamp || listing_config.items_sold == listing_config.token_size {
+    } else if listing_config.is_sold_out() {
+        return err!(AuctioneerError::SoldOut);
+    } else if current_timestamp > listing_config.end_timestamp {
         return err!(AuctioneerError::AuctionEnded);
     }
 
     Ok(())
 }
 
+/// Rejects an order for no units or for more than the listing has left to sell.
+pub fn assert_valid_order_size(listing_config: &ListingConfig, order_size: u64) -> Result<()> {
+    let remaining = listing_config
+        .token_size
+        .saturating_sub(listing_config.items_sold);
+    if order_size == 0 || order_size > remaining {
+        return err!(AuctioneerError::InvalidOrderSize);
+    }
+    Ok(())
+}
+
+/// Rejects a bid that does not cover the reserve price of each of its `order_size` units. No
+/// curve price can be below that, so the bid fails before the curve is priced.
+pub fn assert_covers_reserve(
+    listing_config: &ListingConfig,
+    order_size: u64,
+    max_total_price: u64,
+) -> Result<()> {
+    match order_size.checked_mul(listing_config.reserve_price) {
+        Some(reserve) if reserve <= max_total_price => Ok(()),
+        _ => err!(AuctioneerError::BelowReservePrice),
+    }
+}
+
+/// Checks the pricing parameters of a listing, at creation and on every update.
+pub fn assert_valid_curve(
+    start_price: u64,
+    reserve_price: u64,
+    decay_constant: u64,
+    scale_factor: u64,
+    mode: ListingMode,
+    emission_rate: u64,
+) -> Result<()> {
+    if start_price == 0 {
+        return err!(AuctioneerError::InvalidStartPrice);
+    }
+    // A reserve at or above the start price would clamp every unit, i.e. a fixed-price sale.
+    if reserve_price >= start_price {
+        return err!(AuctioneerError::InvalidReservePrice);
+    }
+    if decay_constant == 0 || decay_constant > MAX_DECAY_CONSTANT {
+        return err!(AuctioneerError::InvalidDecayConstant);
+    }
+    // Discrete prices grow by a = scale_factor / 10_000 per unit sold and divide by a - 1.
+    if mode == ListingMode::Discrete && scale_factor <= BASIS_POINTS {
+        return err!(AuctioneerError::InvalidScaleFactor);
+    }
+    if mode == ListingMode::Continuous && emission_rate == 0 {
+        return err!(AuctioneerError::InvalidEmissionRate);
+    }
+    Ok(())
+}
+
+pub fn assert_end_time_in_future(end_time: UnixTimestamp, now: UnixTimestamp) -> Result<()> {
+    if end_time <= now {
+        return err!(AuctioneerError::EndTimeInPast);
+    }
+    Ok(())
+}
+
 pub fn assert_auction_over(listing_config: &Account<ListingConfig>) -> Result<()> {
     let clock = Clock::get()?;
     let current_timestamp = clock.unix_timestamp;
@@ -66,3 +138,73 @@ pub fn assert_keys_equal(key1: Pubkey, key2: Pubkey) -> Result<()> {
         Ok(())
     }
 }
+
+/// Invokes an Auction House instruction signed by the PDAs of this program in `signer_seeds`,
+/// usually the auctioneer authority and, for listing-side calls, the listing authority. Their
+/// metas are marked as signers; every other account keeps the flag it came in with.
+/// `remaining_accounts` are appended read-only unless they are writable, e.g. creators.
+pub fn invoke_auction_house<'info, A: ToAccountMetas + ToAccountInfos<'info>>(
+    program: &AccountInfo<'info>,
+    accounts: &A,
+    remaining_accounts: &[AccountInfo<'info>],
+    data: Vec<u8>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let signers = signer_seeds
+        .iter()
+        .map(|seeds| Pubkey::create_program_address(seeds, &crate::ID))
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+
+    let mut account_metas: Vec<AccountMeta> = accounts
+        .to_account_metas(None)
+        .into_iter()
+        .zip(accounts.to_account_infos())
+        .map(|(mut meta, info)| {
+            meta.is_signer = info.is_signer || signers.contains(&meta.pubkey);
+            meta
+        })
+        .collect();
+    let mut account_infos = accounts.to_account_infos();
+    for account in remaining_accounts {
+        account_metas.push(AccountMeta {
+            pubkey: account.key(),
+            is_signer: false,
+            is_writable: account.is_writable,
+        });
+        account_infos.push(account.to_account_info());
+    }
+
+    let ix = Instruction {
+        program_id: program.key(),
+        accounts: account_metas,
+        data,
+    };
+    invoke_signed(&ix, &account_infos, signer_seeds)?;
+
+    Ok(())
+}
+
+/// Creates the associated token account of `accounts.authority` for `accounts.mint`, or checks
+/// the one already there. The address is derived from public keys only, so anyone can create
+/// it ahead of the listing.
+pub fn init_associated_token_account<'info>(
+    ata_program: AccountInfo<'info>,
+    accounts: associated_token::Create<'info>,
+) -> Result<()> {
+    let authority = accounts.authority.key();
+    let mint = accounts.mint.key();
+    assert_keys_equal(
+        accounts.associated_token.key(),
+        get_associated_token_address(&authority, &mint),
+    )
+    .map_err(|_e| AuctioneerError::InvalidListingTokenAccount)?;
+    if accounts.associated_token.data_is_empty() {
+        return associated_token::create(CpiContext::new(ata_program, accounts));
+    }
+    let token_account = Account::<TokenAccount>::try_from(&accounts.associated_token)?;
+    if token_account.owner != authority || token_account.mint != mint {
+        return err!(AuctioneerError::InvalidListingTokenAccount);
+    }
+    Ok(())
+}
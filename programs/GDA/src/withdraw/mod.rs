@@ -0,0 +1,169 @@
+use crate::{util::*, AuctionHouse, Auctioneer, *};
+use anchor_spl::associated_token::AssociatedToken;
+
+#[derive(Accounts)]
+#[instruction(escrow_payment_bump: u8, auctioneer_authority_bump: u8, amount: u64)]
+pub struct AuctioneerWithdraw<'info> {
+    /// Auction House Program
+    pub auction_house_program: Program<'info, AuctionHouseProgram>,
+
+    /// Buyer wallet account the escrow belongs to.
+    pub wallet: Signer<'info>,
+
+    /// CHECK: Verified through CPI
+    /// SPL token account or native SOL account to transfer funds to. If the account is a native SOL account, this is the same as the wallet address.
+    #[account(mut)]
+    pub receipt_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Buyer escrow payment account PDA.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            wallet.key().as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump = escrow_payment_bump
+    )]
+    pub escrow_payment_account: UncheckedAccount<'info>,
+
+    /// Auction House instance treasury mint account.
+    pub treasury_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: Verified through CPI
+    /// Auction House instance authority account.
+    pub authority: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// The auctioneer authority - typically a PDA of the Auctioneer program running this action.
+    #[account(seeds = [AUCTIONEER.as_bytes(), auction_house.key().as_ref()], bump = auctioneer_authority_bump)]
+    pub auctioneer_authority: UncheckedAccount<'info>,
+
+    /// Auction House instance PDA account.
+    #[account(
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.creator.as_ref(),
+            auction_house.treasury_mint.as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump=auction_house.bump,
+        has_one=treasury_mint,
+        has_one=auction_house_fee_account
+    )]
+    pub auction_house: Box<Account<'info, AuctionHouse>>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// Auction House instance fee account.
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            auction_house.key().as_ref(),
+            FEE_PAYER.as_bytes()
+        ],
+        seeds::program=auction_house_program,
+        bump=auction_house.fee_payer_bump
+    )]
+    pub auction_house_fee_account: UncheckedAccount<'info>,
+
+    /// CHECK: Not dangerous. Account seeds checked in constraint.
+    /// The auctioneer PDA owned by Auction House storing scopes.
+    #[account(
+        seeds = [
+            AUCTIONEER.as_bytes(),
+            auction_house.key().as_ref(),
+            auctioneer_authority.key().as_ref()
+        ],
+        seeds::program=auction_house_program,
+        bump = ah_auctioneer_pda.bump
+    )]
+    pub ah_auctioneer_pda: Account<'info, Auctioneer>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub ata_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Returns `amount` from the buyer's escrow to `receipt_account`, in lamports for native
+/// auction houses or in treasury mint tokens otherwise.
+pub fn auctioneer_withdraw<'info>(
+    ctx: Context<'_, '_, '_, 'info, AuctioneerWithdraw<'info>>,
+    escrow_payment_bump: u8,
+    auctioneer_authority_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let auction_house = &ctx.accounts.auction_house;
+    let auctioneer_authority = &ctx.accounts.auctioneer_authority;
+    let ah_auctioneer_pda = &ctx.accounts.ah_auctioneer_pda;
+
+    if !auction_house.has_auctioneer {
+        return Err(AuctionHouseError::NoAuctioneerProgramSet.into());
+    }
+
+    assert_valid_auctioneer(
+        auction_house,
+        &auctioneer_authority.key(),
+        ah_auctioneer_pda,
+    )?;
+
+    let accounts = &ctx.accounts;
+    let cpi_accounts = AHWithdraw {
+        wallet: accounts.wallet.to_account_info(),
+        receipt_account: accounts.receipt_account.to_account_info(),
+        escrow_payment_account: accounts.escrow_payment_account.to_account_info(),
+        treasury_mint: accounts.treasury_mint.to_account_info(),
+        authority: accounts.authority.to_account_info(),
+        auctioneer_authority: accounts.auctioneer_authority.to_account_info(),
+        auction_house: accounts.auction_house.to_account_info(),
+        auction_house_fee_account: accounts.auction_house_fee_account.to_account_info(),
+        ah_auctioneer_pda: accounts.ah_auctioneer_pda.to_account_info(),
+        token_program: accounts.token_program.to_account_info(),
+        system_program: accounts.system_program.to_account_info(),
+        ata_program: accounts.ata_program.to_account_info(),
+        rent: accounts.rent.to_account_info(),
+    };
+
+    auctioneer_withdraw_logic(
+        accounts.auction_house_program.to_account_info(),
+        cpi_accounts,
+        escrow_payment_bump,
+        auctioneer_authority_bump,
+        amount,
+    )
+}
+
+/// Invokes Auction House's `auctioneer_withdraw` on the buyer's escrow.
+#[inline(never)]
+pub(crate) fn auctioneer_withdraw_logic<'info>(
+    cpi_program: AccountInfo<'info>,
+    cpi_accounts: AHWithdraw<'info>,
+    escrow_payment_bump: u8,
+    auctioneer_authority_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let withdraw_data = mpl_auction_house::instruction::AuctioneerWithdraw {
+        escrow_payment_bump,
+        amount,
+    };
+
+    let ah_key = cpi_accounts.auction_house.key();
+    let auctioneer_seeds = [
+        AUCTIONEER.as_bytes(),
+        ah_key.as_ref(),
+        &[auctioneer_authority_bump],
+    ];
+
+    invoke_auction_house(
+        &cpi_program,
+        &cpi_accounts,
+        &[],
+        withdraw_data.data(),
+        &[&auctioneer_seeds],
+    )?;
+
+    Ok(())
+}
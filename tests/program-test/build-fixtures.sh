@@ -0,0 +1,26 @@
+#!/usr/bin/env bash
+# Builds the BPF programs the suite loads into tests/fixtures: GDA from this repository, and
+# Auction House and Token Metadata from the crate versions the suite resolves, so all three
+# match the code the tests are written against.
+set -euo pipefail
+
+here="$(cd "$(dirname "$0")" && pwd)"
+root="$(cd "$here/../.." && pwd)"
+fixtures="$here/tests/fixtures"
+mkdir -p "$fixtures"
+
+# Manifest of the registry crate `$1` at the version the suite resolves.
+manifest() {
+    local version
+    version="$(cargo pkgid --manifest-path "$here/Cargo.toml" "$1" | sed 's/.*[@:]//')"
+    cargo metadata --format-version 1 --manifest-path "$here/Cargo.toml" \
+        | grep -o "\"manifest_path\":\"[^\"]*/$1-$version/Cargo.toml\"" \
+        | head -n 1 \
+        | cut -d '"' -f 4
+}
+
+cargo build-bpf --manifest-path "$root/programs/GDA/Cargo.toml" --bpf-out-dir "$fixtures"
+for crate in mpl-auction-house mpl-token-metadata; do
+    CARGO_TARGET_DIR="$root/target/fixtures" \
+        cargo build-bpf --manifest-path "$(manifest "$crate")" --bpf-out-dir "$fixtures"
+done
@@ -0,0 +1,56 @@
+mod common;
+
+use common::*;
+use solana_sdk::signature::Signer;
+use spl_associated_token_account::get_associated_token_address;
+
+const START_PRICE: u64 = 1_000_000;
+const ORDER_SIZE: u64 = 2;
+
+/// Cancelling after a fill hands the unsold units back to the seller and closes the vault, the
+/// listing authority's proceeds account and the `ListingConfig`, rent going to the seller.
+#[tokio::test]
+async fn cancel_returns_the_unsold_units_and_closes_the_listing() {
+    let mut harness = setup(Treasury::Spl).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let price = price(&mut harness, ORDER_SIZE).await;
+    let buy = gda_client::buy(&harness.listing, buyer, ORDER_SIZE, price, &[]);
+    process(&mut harness.context, &[buy], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    let seller = harness.seller.pubkey();
+    let seller_tokens = get_associated_token_address(&seller, &harness.listing.token_mint);
+    let vault = harness.listing.vault();
+    let proceeds_account = harness.listing.listing_proceeds_account();
+    let listing_config = harness.listing.listing_config().0;
+    let rent = lamports(&mut harness.context, &vault).await
+        + lamports(&mut harness.context, &proceeds_account).await
+        + lamports(&mut harness.context, &listing_config).await;
+    let seller_before = lamports(&mut harness.context, &seller).await;
+
+    let cancel = gda_client::auctioneer_cancel(&harness.listing);
+    process(&mut harness.context, &[cancel], &[&harness.seller])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        token_balance(&mut harness.context, &seller_tokens).await,
+        LISTING_SIZE - ORDER_SIZE
+    );
+    for closed in [vault, proceeds_account, listing_config] {
+        assert!(harness
+            .context
+            .banks_client
+            .get_account(closed)
+            .await
+            .unwrap()
+            .is_none());
+    }
+    assert_eq!(
+        lamports(&mut harness.context, &seller).await - seller_before,
+        rent
+    );
+}
@@ -0,0 +1,386 @@
+#![allow(dead_code)]
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use gda_client::{AuctionHouseKeys, ListingConfig, ListingKeys, ListingMode, SellParams};
+use mpl_auction_house::{pda::find_auction_house_address, AuthorityScope};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    instruction::{Instruction, InstructionError},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    system_instruction, system_program, sysvar,
+    transaction::{Transaction, TransactionError},
+    transport::TransportError,
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+use spl_token::state::{Account as TokenAccount, Mint};
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+/// Auction House fee on every sale, in basis points.
+pub const MARKETPLACE_FEE_BPS: u16 = 200;
+pub const LISTING_SIZE: u64 = 5;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Treasury {
+    Native,
+    Spl,
+}
+
+pub struct Harness {
+    pub context: ProgramTestContext,
+    pub auction_house: AuctionHouseKeys,
+    pub listing: ListingKeys,
+    pub seller: Keypair,
+    pub buyer: Keypair,
+}
+
+/// Starts a bank with the BPF builds of GDA, Auction House and Token Metadata, an Auction House
+/// for `treasury` that delegates to the GDA auctioneer, and a seller and buyer with funds.
+/// program-test bundles SPL Token and Associated Token Account itself.
+pub async fn setup(treasury: Treasury) -> Harness {
+    let mut program_test = ProgramTest::default();
+    program_test.prefer_bpf(true);
+    program_test.add_program("gda", gda::ID, None);
+    program_test.add_program("mpl_auction_house", mpl_auction_house::ID, None);
+    program_test.add_program("mpl_token_metadata", mpl_token_metadata::ID, None);
+    add_native_mint(&mut program_test);
+    let mut context = program_test.start_with_context().await;
+
+    let authority = context.payer.pubkey();
+    let seller = Keypair::new();
+    let buyer = Keypair::new();
+    let fund = [&seller, &buyer].map(|wallet| {
+        system_instruction::transfer(&authority, &wallet.pubkey(), 10 * LAMPORTS_PER_SOL)
+    });
+    process(&mut context, &fund, &[]).await.unwrap();
+
+    let treasury_mint = match treasury {
+        Treasury::Native => spl_token::native_mint::ID,
+        Treasury::Spl => {
+            let mint = create_mint(&mut context, 6).await;
+            for wallet in [&seller.pubkey(), &buyer.pubkey()] {
+                create_token_account(&mut context, wallet, &mint).await;
+            }
+            mint_to(&mut context, &mint, &buyer.pubkey(), 1_000 * 1_000_000).await;
+            mint
+        }
+    };
+    let auction_house = create_auction_house(&mut context, treasury_mint).await;
+
+    let token_mint = create_mint(&mut context, 0).await;
+    create_token_account(&mut context, &seller.pubkey(), &token_mint).await;
+    mint_to(&mut context, &token_mint, &seller.pubkey(), LISTING_SIZE).await;
+    create_metadata(&mut context, &token_mint, &seller.pubkey()).await;
+
+    Harness {
+        context,
+        auction_house,
+        listing: ListingKeys {
+            auction_house,
+            seller: seller.pubkey(),
+            token_mint,
+            token_size: LISTING_SIZE,
+        },
+        seller,
+        buyer,
+    }
+}
+
+fn add_native_mint(program_test: &mut ProgramTest) {
+    let mut data = vec![0; Mint::LEN];
+    Mint {
+        mint_authority: COption::None,
+        supply: 0,
+        decimals: 9,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    program_test.add_account(
+        spl_token::native_mint::ID,
+        Account {
+            lamports: Rent::default().minimum_balance(Mint::LEN),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+}
+
+/// Sends `instructions` in one transaction paid by the context payer.
+pub async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), TransportError> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+/// Asserts that the first instruction of the transaction failed with the program error `code`.
+pub fn assert_program_error(result: Result<(), TransportError>, code: impl Into<u32>) {
+    let code = code.into();
+    match result {
+        Err(TransportError::TransactionError(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(error),
+        ))) => assert_eq!(error, code),
+        other => panic!("expected custom error {}, got {:?}", code, other),
+    }
+}
+
+pub async fn create_mint(context: &mut ProgramTestContext, decimals: u8) -> Pubkey {
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let instructions = [
+        system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            rent.minimum_balance(Mint::LEN),
+            Mint::LEN as u64,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::ID,
+            &mint.pubkey(),
+            &payer,
+            None,
+            decimals,
+        )
+        .unwrap(),
+    ];
+    process(context, &instructions, &[&mint]).await.unwrap();
+    mint.pubkey()
+}
+
+pub async fn create_token_account(
+    context: &mut ProgramTestContext,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+) {
+    let ix = create_associated_token_account(&context.payer.pubkey(), wallet, mint);
+    process(context, &[ix], &[]).await.unwrap();
+}
+
+pub async fn mint_to(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    wallet: &Pubkey,
+    amount: u64,
+) {
+    let ix = spl_token::instruction::mint_to(
+        &spl_token::ID,
+        mint,
+        &get_associated_token_address(wallet, mint),
+        &context.payer.pubkey(),
+        &[],
+        amount,
+    )
+    .unwrap();
+    process(context, &[ix], &[]).await.unwrap();
+}
+
+async fn create_metadata(
+    context: &mut ProgramTestContext,
+    mint: &Pubkey,
+    update_authority: &Pubkey,
+) {
+    let payer = context.payer.pubkey();
+    let ix = mpl_token_metadata::instruction::create_metadata_accounts_v2(
+        mpl_token_metadata::ID,
+        gda_client::find_metadata_account(mint).0,
+        *mint,
+        payer,
+        payer,
+        *update_authority,
+        "GDA".to_string(),
+        "GDA".to_string(),
+        "https://example.com/gda.json".to_string(),
+        None,
+        0,
+        false,
+        true,
+        None,
+        None,
+    );
+    process(context, &[ix], &[]).await.unwrap();
+}
+
+/// Creates an Auction House owned by the context payer and delegates every scope to the GDA
+/// auctioneer authority.
+async fn create_auction_house(
+    context: &mut ProgramTestContext,
+    treasury_mint: Pubkey,
+) -> AuctionHouseKeys {
+    let authority = context.payer.pubkey();
+    let (address, bump) = find_auction_house_address(&authority, &treasury_mint);
+    let auction_house = AuctionHouseKeys {
+        address,
+        authority,
+        treasury_mint,
+    };
+    let (auction_house_fee_account, fee_payer_bump) =
+        gda_client::find_auction_house_fee_account_address(&address);
+    let (auction_house_treasury, treasury_bump) =
+        gda_client::find_auction_house_treasury_address(&address);
+
+    let create = Instruction {
+        program_id: mpl_auction_house::ID,
+        accounts: mpl_auction_house::accounts::CreateAuctionHouse {
+            treasury_mint,
+            payer: authority,
+            authority,
+            fee_withdrawal_destination: authority,
+            treasury_withdrawal_destination: auction_house.payment_account(&authority),
+            treasury_withdrawal_destination_owner: authority,
+            auction_house: address,
+            auction_house_fee_account,
+            auction_house_treasury,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            ata_program: spl_associated_token_account::ID,
+            rent: sysvar::rent::ID,
+        }
+        .to_account_metas(None),
+        data: mpl_auction_house::instruction::CreateAuctionHouse {
+            _bump: bump,
+            fee_payer_bump,
+            treasury_bump,
+            seller_fee_basis_points: MARKETPLACE_FEE_BPS,
+            requires_sign_off: false,
+            can_change_sale_price: false,
+        }
+        .data(),
+    };
+    let fund_fee_account =
+        system_instruction::transfer(&authority, &auction_house_fee_account, LAMPORTS_PER_SOL);
+    // A native treasury is a plain system account, so fees alone would leave it rent-paying.
+    let fund_treasury = system_instruction::transfer(
+        &authority,
+        &auction_house_treasury,
+        Rent::default().minimum_balance(0),
+    );
+    let delegate = Instruction {
+        program_id: mpl_auction_house::ID,
+        accounts: mpl_auction_house::accounts::DelegateAuctioneer {
+            auction_house: address,
+            authority,
+            auctioneer_authority: auction_house.auctioneer_authority().0,
+            ah_auctioneer_pda: auction_house.ah_auctioneer_pda(),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: mpl_auction_house::instruction::DelegateAuctioneer {
+            scopes: vec![
+                AuthorityScope::Deposit,
+                AuthorityScope::Buy,
+                AuthorityScope::PublicBuy,
+                AuthorityScope::ExecuteSale,
+                AuthorityScope::Sell,
+                AuthorityScope::Cancel,
+                AuthorityScope::Withdraw,
+            ],
+        }
+        .data(),
+    };
+    let mut instructions = vec![create, fund_fee_account, delegate];
+    if auction_house.is_native() {
+        instructions.push(fund_treasury);
+    }
+    process(context, &instructions, &[]).await.unwrap();
+    auction_house
+}
+
+/// Lists every unit the seller holds on a discrete curve starting now and running for a day.
+pub async fn list(harness: &mut Harness, start_price: u64) {
+    let now = clock(&mut harness.context).await.unix_timestamp;
+    let ix = gda_client::auctioneer_sell(
+        &harness.listing,
+        get_associated_token_address(&harness.seller.pubkey(), &harness.listing.token_mint),
+        SellParams {
+            start_time: now,
+            end_time: now + 86_400,
+            start_price,
+            reserve_price: start_price / 10,
+            decay_constant: 36_000,
+            scale_factor: 12_000,
+            mode: ListingMode::Discrete,
+            emission_rate: 0,
+        },
+    );
+    process(&mut harness.context, &[ix], &[&harness.seller])
+        .await
+        .unwrap();
+}
+
+pub async fn clock(context: &mut ProgramTestContext) -> Clock {
+    context.banks_client.get_sysvar::<Clock>().await.unwrap()
+}
+
+/// Moves the bank ahead a couple of slots, with a fresh blockhash, and its clock `seconds` ahead of
+/// the current one, as if that much time had passed.
+pub async fn warp(context: &mut ProgramTestContext, seconds: i64) {
+    let before = clock(context).await;
+    context.warp_to_slot(before.slot + 2).unwrap();
+    let mut clock = clock(context).await;
+    clock.unix_timestamp = before.unix_timestamp + seconds;
+    context.set_sysvar(&clock);
+}
+
+pub async fn listing_config(harness: &mut Harness) -> ListingConfig {
+    let address = harness.listing.listing_config().0;
+    let account = harness
+        .context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .expect("listing config not found");
+    gda_client::decode_listing_config(&account.data).unwrap()
+}
+
+/// The cumulative price the program will charge for `order_size` units in the current slot.
+pub async fn price(harness: &mut Harness, order_size: u64) -> u64 {
+    let now = clock(&mut harness.context).await.unix_timestamp;
+    listing_config(harness)
+        .await
+        .calculate_price_at(order_size, now)
+        .unwrap()
+}
+
+pub async fn lamports(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    context.banks_client.get_balance(*address).await.unwrap()
+}
+
+pub async fn token_balance(context: &mut ProgramTestContext, address: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .expect("token account not found");
+    TokenAccount::unpack(&account.data).unwrap().amount
+}
+
+/// Balance of `account` in the treasury currency: lamports, or treasury mint tokens.
+pub async fn treasury_balance(harness: &mut Harness, account: &Pubkey) -> u64 {
+    if harness.auction_house.is_native() {
+        lamports(&mut harness.context, account).await
+    } else {
+        token_balance(&mut harness.context, account).await
+    }
+}
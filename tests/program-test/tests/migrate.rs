@@ -0,0 +1,89 @@
+mod common;
+
+use anchor_lang::{AccountSerialize, AnchorSerialize, Discriminator};
+use common::*;
+use gda::{errors::AuctioneerError, migrate::ListingConfigV0, ListingConfig, ListingMode};
+use solana_sdk::{account::AccountSharedData, pubkey::Pubkey, rent::Rent, signature::Signer};
+
+// Writes `data` to the listing's config address as a rent exempt account of this program.
+fn set_listing_config(harness: &mut Harness, data: Vec<u8>) -> Pubkey {
+    let address = harness.listing.listing_config().0;
+    let mut account = AccountSharedData::new(
+        Rent::default().minimum_balance(data.len()),
+        data.len(),
+        &gda::ID,
+    );
+    account.set_data(data);
+    harness.context.set_account(&address, &account);
+    address
+}
+
+/// A listing in the unversioned layout is grown to the current size and converted.
+#[tokio::test]
+async fn migrate_unversioned_listing_config() {
+    let mut harness = setup(Treasury::Native).await;
+    let legacy = ListingConfigV0 {
+        token_size: LISTING_SIZE,
+        items_sold: 2,
+        start_price: 1_000_000,
+        decay_const: 1,
+        scale_factor: 2,
+        first_init_timestamp: 100,
+        end_timestamp: 200,
+        bump: 254,
+    };
+    let mut data = ListingConfig::discriminator().to_vec();
+    legacy.serialize(&mut data).unwrap();
+    let address = set_listing_config(&mut harness, data);
+
+    let payer = harness.buyer.pubkey();
+    let migrate = gda_client::migrate_listing_config(address, payer);
+    process(&mut harness.context, &[migrate], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    let account = harness
+        .context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), ListingConfig::LEN);
+    assert_eq!(
+        account.lamports,
+        Rent::default().minimum_balance(ListingConfig::LEN)
+    );
+    let listing_config = listing_config(&mut harness).await;
+    assert_eq!(listing_config.version, ListingConfig::VERSION);
+    assert_eq!(listing_config.token_size, LISTING_SIZE);
+    assert_eq!(listing_config.items_sold, 2);
+    assert_eq!(listing_config.start_price, 1_000_000);
+    assert_eq!(listing_config.reserve_price, 0);
+    assert_eq!(listing_config.decay_const, gda::MAX_DECAY_CONSTANT);
+    assert_eq!(listing_config.scale_factor, 20_000);
+    assert_eq!(listing_config.first_init_timestamp, 100);
+    assert_eq!(listing_config.end_timestamp, 200);
+    assert_eq!(listing_config.mode, ListingMode::Discrete);
+    assert_eq!(listing_config.bump, 254);
+    assert_eq!(
+        listing_config.listing_authority_bump,
+        harness.listing.listing_authority().1
+    );
+}
+
+#[tokio::test]
+async fn migrate_listing_config_from_a_newer_version_fails() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, 1_000_000).await;
+    let mut newer = listing_config(&mut harness).await;
+    newer.version = ListingConfig::VERSION + 1;
+    let mut data = Vec::new();
+    newer.try_serialize(&mut data).unwrap();
+    data.resize(ListingConfig::LEN, 0);
+    let address = set_listing_config(&mut harness, data);
+
+    let migrate = gda_client::migrate_listing_config(address, harness.buyer.pubkey());
+    let result = process(&mut harness.context, &[migrate], &[&harness.buyer]).await;
+    assert_program_error(result, AuctioneerError::UnsupportedListingVersion);
+}
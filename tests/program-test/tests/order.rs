@@ -0,0 +1,89 @@
+mod common;
+
+use common::*;
+use gda::errors::AuctioneerError;
+use solana_sdk::signature::Signer;
+
+const START_PRICE: u64 = 1_000_000;
+
+// The order size the program computes for `budget` right now.
+async fn quantity_for_budget(harness: &mut Harness, budget: u64) -> u64 {
+    let now = clock(&mut harness.context).await.unix_timestamp;
+    listing_config(harness)
+        .await
+        .max_quantity_for_budget(budget, now)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn place_order_with_budget() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let budget = price(&mut harness, 3).await;
+    let order_size = quantity_for_budget(&mut harness, budget).await;
+    assert_eq!(order_size, 3);
+    let place_order =
+        gda_client::place_order_with_budget(&harness.listing, buyer, order_size, budget);
+    process(&mut harness.context, &[place_order], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    let buyer_trade_state = harness
+        .listing
+        .buyer_trade_state(&buyer, budget, order_size)
+        .0;
+    assert!(lamports(&mut harness.context, &buyer_trade_state).await > 0);
+}
+
+/// The buyer trade state is derived off-chain, so a budget order whose size was predicted
+/// wrong is rejected before Auction House is called.
+#[tokio::test]
+async fn place_order_with_budget_for_another_size_fails() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let budget = price(&mut harness, 3).await;
+    let place_order = gda_client::place_order_with_budget(&harness.listing, buyer, 2, budget);
+    let result = process(&mut harness.context, &[place_order], &[&harness.buyer]).await;
+    assert_program_error(result, AuctioneerError::OrderSizeMismatch);
+}
+
+/// `list` sets the reserve to a tenth of the start price, so no bid under that per unit can
+/// ever settle.
+#[tokio::test]
+async fn place_order_below_reserve_price_fails() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let reserve_price = listing_config(&mut harness).await.reserve_price;
+    let place_order = gda_client::place_order(&harness.listing, buyer, 2, 2 * reserve_price - 1);
+    let result = process(&mut harness.context, &[place_order], &[&harness.buyer]).await;
+    assert_program_error(result, AuctioneerError::BelowReservePrice);
+}
+
+#[tokio::test]
+async fn place_order_for_no_units_fails() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let place_order = gda_client::place_order(&harness.listing, buyer, 0, START_PRICE);
+    let result = process(&mut harness.context, &[place_order], &[&harness.buyer]).await;
+    assert_program_error(result, AuctioneerError::InvalidOrderSize);
+}
+
+#[tokio::test]
+async fn place_order_for_more_than_the_listing_fails() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let place_order =
+        gda_client::place_order(&harness.listing, buyer, LISTING_SIZE + 1, u64::MAX / 2);
+    let result = process(&mut harness.context, &[place_order], &[&harness.buyer]).await;
+    assert_program_error(result, AuctioneerError::InvalidOrderSize);
+}
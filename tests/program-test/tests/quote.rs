@@ -0,0 +1,78 @@
+mod common;
+
+use anchor_lang::AnchorDeserialize;
+use common::*;
+use gda::{errors::AuctioneerError, quote::PriceQuote};
+use solana_program_test::BanksClientError;
+use solana_sdk::{
+    instruction::InstructionError,
+    signature::Signer,
+    transaction::{Transaction, TransactionError},
+};
+
+const START_PRICE: u64 = 1_000_000;
+
+// Quotes `order_size` units and reads the `PriceQuote` back from the "Program return" log.
+// Banks only hands back the logs of transactions that fail, so a second quote for more units
+// than the listing holds fails the transaction once the first has set its return data.
+async fn quote(harness: &mut Harness, order_size: u64) -> PriceQuote {
+    let listing_config = harness.listing.listing_config().0;
+    let transaction = Transaction::new_signed_with_payer(
+        &[
+            gda_client::quote(listing_config, order_size),
+            gda_client::quote(listing_config, LISTING_SIZE + 1),
+        ],
+        Some(&harness.context.payer.pubkey()),
+        &[&harness.context.payer],
+        harness.context.last_blockhash,
+    );
+    let logs = match harness
+        .context
+        .banks_client
+        .process_transaction_with_preflight(transaction)
+        .await
+    {
+        Err(BanksClientError::SimulationError { err, logs, .. }) => {
+            assert_eq!(
+                err,
+                TransactionError::InstructionError(
+                    1,
+                    InstructionError::Custom(AuctioneerError::InsufficientEmission.into())
+                )
+            );
+            logs
+        }
+        other => panic!("expected the second quote to fail, got {:?}", other),
+    };
+    let prefix = format!("Program return: {} ", gda::ID);
+    let data = logs
+        .iter()
+        .find_map(|log| log.strip_prefix(&prefix))
+        .expect("no return data");
+    PriceQuote::try_from_slice(&base64::decode(data).unwrap()).unwrap()
+}
+
+/// `quote` prices orders with the same `calculate_price_at` as settlement.
+#[tokio::test]
+async fn quote_matches_calculate_price_at() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+    warp(&mut harness.context, 600).await;
+
+    let now = clock(&mut harness.context).await.unix_timestamp;
+    let listing_config = listing_config(&mut harness).await;
+    for order_size in 1..=LISTING_SIZE {
+        let expected_price = listing_config.calculate_price_at(order_size, now).unwrap();
+        let previous_price = listing_config
+            .calculate_price_at(order_size - 1, now)
+            .unwrap();
+        assert_eq!(
+            quote(&mut harness, order_size).await,
+            PriceQuote {
+                cumulative_price: expected_price,
+                marginal_price: expected_price - previous_price,
+                available_supply: LISTING_SIZE,
+            }
+        );
+    }
+}
@@ -0,0 +1,316 @@
+mod common;
+
+use common::*;
+use gda::errors::AuctioneerError;
+use solana_sdk::signature::Signer;
+use spl_associated_token_account::get_associated_token_address;
+
+const START_PRICE: u64 = 1_000_000;
+const ORDER_SIZE: u64 = 2;
+
+/// Lists, bids above the current curve price and settles at the curve price, then checks
+/// where the units, the proceeds and the refund of the rest of the bid ended up.
+async fn sell_place_order_and_execute_sale(treasury: Treasury) {
+    let mut harness = setup(treasury).await;
+    list(&mut harness, START_PRICE).await;
+    let vault = harness.listing.vault();
+    assert_eq!(
+        token_balance(&mut harness.context, &vault).await,
+        LISTING_SIZE
+    );
+
+    let buyer = harness.buyer.pubkey();
+    let buyer_account = harness.auction_house.payment_account(&buyer);
+    let buyer_before = treasury_balance(&mut harness, &buyer_account).await;
+    let price = price(&mut harness, ORDER_SIZE).await;
+    let max_total_price = price + price / 10;
+    let place_order = gda_client::place_order(&harness.listing, buyer, ORDER_SIZE, max_total_price);
+    process(&mut harness.context, &[place_order], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    let seller_account = harness
+        .auction_house
+        .payment_account(&harness.listing.seller);
+    let treasury_account = harness.auction_house.treasury();
+    let seller_before = treasury_balance(&mut harness, &seller_account).await;
+    let treasury_before = treasury_balance(&mut harness, &treasury_account).await;
+
+    let execute_sale = gda_client::auctioneer_execute_sale(
+        &harness.listing,
+        buyer,
+        max_total_price,
+        ORDER_SIZE,
+        &[],
+    );
+    process(&mut harness.context, &[execute_sale], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    // Auction House executes at the bid, so its fee is charged on the whole of it.
+    let fee = max_total_price * u64::from(MARKETPLACE_FEE_BPS) / 10_000;
+    let seller_after = treasury_balance(&mut harness, &seller_account).await;
+    let treasury_after = treasury_balance(&mut harness, &treasury_account).await;
+    assert_eq!(seller_after - seller_before, price - fee);
+    assert_eq!(treasury_after - treasury_before, fee);
+    if !harness.auction_house.is_native() {
+        // Native buyers also pay rent and fees, so only SPL balances add up exactly.
+        let buyer_after = treasury_balance(&mut harness, &buyer_account).await;
+        assert_eq!(buyer_before - buyer_after, price);
+    }
+
+    let buyer_tokens = get_associated_token_address(&buyer, &harness.listing.token_mint);
+    assert_eq!(
+        token_balance(&mut harness.context, &buyer_tokens).await,
+        ORDER_SIZE
+    );
+    assert_eq!(
+        token_balance(&mut harness.context, &vault).await,
+        LISTING_SIZE - ORDER_SIZE
+    );
+    let escrow = harness.auction_house.escrow_payment_account(&buyer).0;
+    if !harness.auction_house.is_native() {
+        assert_eq!(token_balance(&mut harness.context, &escrow).await, 0);
+    }
+    let listing_config = listing_config(&mut harness).await;
+    assert_eq!(listing_config.items_sold, ORDER_SIZE);
+}
+
+#[tokio::test]
+async fn native_treasury() {
+    sell_place_order_and_execute_sale(Treasury::Native).await;
+}
+
+#[tokio::test]
+async fn spl_treasury() {
+    sell_place_order_and_execute_sale(Treasury::Spl).await;
+}
+
+#[tokio::test]
+async fn place_order_above_max_total_price_fails() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let price = price(&mut harness, ORDER_SIZE).await;
+    let place_order = gda_client::place_order(&harness.listing, buyer, ORDER_SIZE, price - 1);
+    let result = process(&mut harness.context, &[place_order], &[&harness.buyer]).await;
+    assert_program_error(result, AuctioneerError::PriceExceedsLimit);
+}
+
+/// The curve keeps decaying after the listing goes up, so a bid priced for later goes through
+/// once that time has passed.
+#[tokio::test]
+async fn price_decays_over_time() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    // 1e6 * (1 + 1.2) = 2,200,000 at listing time.
+    let decayed_price = 1_207_386;
+    let place_order = gda_client::place_order(&harness.listing, buyer, ORDER_SIZE, decayed_price);
+    let result = process(
+        &mut harness.context,
+        std::slice::from_ref(&place_order),
+        &[&harness.buyer],
+    )
+    .await;
+    assert_program_error(result, AuctioneerError::PriceExceedsLimit);
+
+    // 2,200,000 * e^(-0.001 * 600) = 1,207,385.60, rounded up.
+    warp(&mut harness.context, 600).await;
+    assert_eq!(price(&mut harness, ORDER_SIZE).await, decayed_price);
+    process(&mut harness.context, &[place_order], &[&harness.buyer])
+        .await
+        .unwrap();
+}
+
+/// Bids the current price for `order_size` units and settles the bid straight away.
+async fn fill(harness: &mut Harness, order_size: u64) {
+    let buyer = harness.buyer.pubkey();
+    let price = price(harness, order_size).await;
+    let place_order = gda_client::place_order(&harness.listing, buyer, order_size, price);
+    let execute_sale =
+        gda_client::auctioneer_execute_sale(&harness.listing, buyer, price, order_size, &[]);
+    process(
+        &mut harness.context,
+        &[place_order, execute_sale],
+        &[&harness.buyer],
+    )
+    .await
+    .unwrap();
+
+    // Auction House never closes trade states itself, so settlement cancels them every time.
+    let trade_states = [
+        harness
+            .listing
+            .buyer_trade_state(&buyer, price, order_size)
+            .0,
+        harness.listing.seller_trade_state(order_size).0,
+        harness.listing.free_trade_state(order_size).0,
+    ];
+    for trade_state in trade_states {
+        assert_eq!(lamports(&mut harness.context, &trade_state).await, 0);
+    }
+}
+
+#[tokio::test]
+async fn partial_fill_then_final_fill() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+    let vault = harness.listing.vault();
+
+    fill(&mut harness, ORDER_SIZE).await;
+    assert_eq!(
+        token_balance(&mut harness.context, &vault).await,
+        LISTING_SIZE - ORDER_SIZE
+    );
+
+    fill(&mut harness, LISTING_SIZE - ORDER_SIZE).await;
+    assert_eq!(token_balance(&mut harness.context, &vault).await, 0);
+    let buyer_tokens =
+        get_associated_token_address(&harness.buyer.pubkey(), &harness.listing.token_mint);
+    assert_eq!(
+        token_balance(&mut harness.context, &buyer_tokens).await,
+        LISTING_SIZE
+    );
+    assert!(listing_config(&mut harness).await.is_sold_out());
+
+    // Nothing is left to sell, so further orders are turned away.
+    let place_order =
+        gda_client::place_order(&harness.listing, harness.buyer.pubkey(), 1, START_PRICE);
+    let result = process(&mut harness.context, &[place_order], &[&harness.buyer]).await;
+    assert_program_error(result, AuctioneerError::SoldOut);
+}
+
+/// The bid's trade state is derived from the bid rather than from the price at settlement, so
+/// a bid settled after the curve has moved still goes through, at the later price.
+#[tokio::test]
+async fn execute_sale_after_the_price_moved() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let max_total_price = price(&mut harness, ORDER_SIZE).await;
+    let place_order = gda_client::place_order(&harness.listing, buyer, ORDER_SIZE, max_total_price);
+    process(&mut harness.context, &[place_order], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    warp(&mut harness.context, 600).await;
+    let settled_price = price(&mut harness, ORDER_SIZE).await;
+    assert!(settled_price < max_total_price);
+    let seller = harness.listing.seller;
+    let seller_before = lamports(&mut harness.context, &seller).await;
+    let execute_sale = gda_client::auctioneer_execute_sale(
+        &harness.listing,
+        buyer,
+        max_total_price,
+        ORDER_SIZE,
+        &[],
+    );
+    process(&mut harness.context, &[execute_sale], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    let fee = max_total_price * u64::from(MARKETPLACE_FEE_BPS) / 10_000;
+    let seller_after = lamports(&mut harness.context, &seller).await;
+    assert_eq!(seller_after - seller_before, settled_price - fee);
+    assert_eq!(listing_config(&mut harness).await.items_sold, ORDER_SIZE);
+}
+
+#[tokio::test]
+async fn execute_sale_after_the_auction_ended_fails() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let max_total_price = price(&mut harness, ORDER_SIZE).await;
+    let place_order = gda_client::place_order(&harness.listing, buyer, ORDER_SIZE, max_total_price);
+    process(&mut harness.context, &[place_order], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    let end = listing_config(&mut harness).await.end_timestamp;
+    let now = clock(&mut harness.context).await.unix_timestamp;
+    warp(&mut harness.context, end - now + 1).await;
+    let execute_sale = gda_client::auctioneer_execute_sale(
+        &harness.listing,
+        buyer,
+        max_total_price,
+        ORDER_SIZE,
+        &[],
+    );
+    let result = process(&mut harness.context, &[execute_sale], &[&harness.buyer]).await;
+    assert_program_error(result, AuctioneerError::AuctionEnded);
+}
+
+/// `buy` bids and settles in one go: the buyer pays the curve price, and the seller gets it
+/// less Auction House's fee on the whole bid.
+#[tokio::test]
+async fn buy_now() {
+    let mut harness = setup(Treasury::Spl).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let buyer_account = harness.auction_house.payment_account(&buyer);
+    let seller_account = harness
+        .auction_house
+        .payment_account(&harness.listing.seller);
+    let buyer_before = treasury_balance(&mut harness, &buyer_account).await;
+    let seller_before = treasury_balance(&mut harness, &seller_account).await;
+
+    let price = price(&mut harness, ORDER_SIZE).await;
+    let max_total_price = price + price / 10;
+    let buy = gda_client::buy(&harness.listing, buyer, ORDER_SIZE, max_total_price, &[]);
+    process(&mut harness.context, &[buy], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    let fee = max_total_price * u64::from(MARKETPLACE_FEE_BPS) / 10_000;
+    let buyer_after = treasury_balance(&mut harness, &buyer_account).await;
+    let seller_after = treasury_balance(&mut harness, &seller_account).await;
+    assert_eq!(buyer_before - buyer_after, price);
+    assert_eq!(seller_after - seller_before, price - fee);
+
+    let buyer_tokens = get_associated_token_address(&buyer, &harness.listing.token_mint);
+    assert_eq!(
+        token_balance(&mut harness.context, &buyer_tokens).await,
+        ORDER_SIZE
+    );
+    let escrow = harness.auction_house.escrow_payment_account(&buyer).0;
+    assert_eq!(token_balance(&mut harness.context, &escrow).await, 0);
+    assert_eq!(listing_config(&mut harness).await.items_sold, ORDER_SIZE);
+}
+
+/// A bid priced at listing time and settled after the curve decayed gets the decay back.
+#[tokio::test]
+async fn refund_after_the_price_decayed() {
+    let mut harness = setup(Treasury::Spl).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let buyer_account = harness.auction_house.payment_account(&buyer);
+    let max_total_price = price(&mut harness, ORDER_SIZE).await;
+    let place_order = gda_client::place_order(&harness.listing, buyer, ORDER_SIZE, max_total_price);
+    process(&mut harness.context, &[place_order], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    warp(&mut harness.context, 600).await;
+    let settled_price = price(&mut harness, ORDER_SIZE).await;
+    let buyer_before = treasury_balance(&mut harness, &buyer_account).await;
+    let execute_sale = gda_client::auctioneer_execute_sale(
+        &harness.listing,
+        buyer,
+        max_total_price,
+        ORDER_SIZE,
+        &[],
+    );
+    process(&mut harness.context, &[execute_sale], &[&harness.buyer])
+        .await
+        .unwrap();
+
+    let refund = treasury_balance(&mut harness, &buyer_account).await - buyer_before;
+    assert_eq!(refund, max_total_price - settled_price);
+}
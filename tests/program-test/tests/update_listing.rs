@@ -0,0 +1,90 @@
+mod common;
+
+use common::*;
+use gda::errors::AuctioneerError;
+use gda_client::CurveUpdate;
+use solana_sdk::{signature::Signer, transport::TransportError};
+
+const START_PRICE: u64 = 1_000_000;
+
+// The listing's current curve, to be edited by the test.
+async fn current_curve(harness: &mut Harness) -> CurveUpdate {
+    let listing_config = listing_config(harness).await;
+    CurveUpdate {
+        start_price: listing_config.start_price,
+        reserve_price: listing_config.reserve_price,
+        decay_constant: listing_config.decay_const,
+        scale_factor: listing_config.scale_factor,
+        end_time: listing_config.end_timestamp,
+    }
+}
+
+async fn update(harness: &mut Harness, update: CurveUpdate) -> Result<(), TransportError> {
+    let update_listing = gda_client::update_listing(&harness.listing, update);
+    process(&mut harness.context, &[update_listing], &[&harness.seller]).await
+}
+
+async fn sell_one_unit(harness: &mut Harness) {
+    let price = price(harness, 1).await;
+    let buy = gda_client::buy(&harness.listing, harness.buyer.pubkey(), 1, price, &[]);
+    process(&mut harness.context, &[buy], &[&harness.buyer])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn update_listing_before_the_first_sale() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+
+    let curve = current_curve(&mut harness).await;
+    let new_curve = CurveUpdate {
+        start_price: 2 * START_PRICE,
+        reserve_price: 0,
+        decay_constant: 2 * curve.decay_constant,
+        scale_factor: 11_000,
+        end_time: curve.end_time - 3_600,
+    };
+    update(&mut harness, new_curve).await.unwrap();
+
+    let listing_config = listing_config(&mut harness).await;
+    assert_eq!(listing_config.start_price, new_curve.start_price);
+    assert_eq!(listing_config.reserve_price, new_curve.reserve_price);
+    assert_eq!(listing_config.decay_const, new_curve.decay_constant);
+    assert_eq!(listing_config.scale_factor, new_curve.scale_factor);
+    assert_eq!(listing_config.end_timestamp, new_curve.end_time);
+}
+
+/// Once a unit has sold, buyers have paid along the current curve, so only the end time can
+/// move, and only later.
+#[tokio::test]
+async fn update_listing_after_a_sale() {
+    let mut harness = setup(Treasury::Native).await;
+    list(&mut harness, START_PRICE).await;
+    sell_one_unit(&mut harness).await;
+
+    let curve = current_curve(&mut harness).await;
+    let repriced = CurveUpdate {
+        start_price: 2 * START_PRICE,
+        ..curve
+    };
+    let result = update(&mut harness, repriced).await;
+    assert_program_error(result, AuctioneerError::CurveLocked);
+
+    let shortened = CurveUpdate {
+        end_time: curve.end_time - 1,
+        ..curve
+    };
+    let result = update(&mut harness, shortened).await;
+    assert_program_error(result, AuctioneerError::CannotShortenAuction);
+
+    let extended = CurveUpdate {
+        end_time: curve.end_time + 3_600,
+        ..curve
+    };
+    update(&mut harness, extended).await.unwrap();
+    assert_eq!(
+        listing_config(&mut harness).await.end_timestamp,
+        extended.end_time
+    );
+}
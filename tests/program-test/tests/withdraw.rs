@@ -0,0 +1,56 @@
+mod common;
+
+use common::*;
+use solana_sdk::signature::Signer;
+
+const START_PRICE: u64 = 1_000_000;
+const ORDER_SIZE: u64 = 2;
+
+/// A buyer who cancels a resting bid gets the escrowed funds back with `auctioneer_withdraw`.
+#[tokio::test]
+async fn withdraw_returns_the_escrowed_bid() {
+    let mut harness = setup(Treasury::Spl).await;
+    list(&mut harness, START_PRICE).await;
+
+    let buyer = harness.buyer.pubkey();
+    let buyer_account = harness.auction_house.payment_account(&buyer);
+    let escrow = harness.auction_house.escrow_payment_account(&buyer).0;
+    let buyer_before = treasury_balance(&mut harness, &buyer_account).await;
+    let max_total_price = price(&mut harness, ORDER_SIZE).await;
+    let place_order = gda_client::place_order(&harness.listing, buyer, ORDER_SIZE, max_total_price);
+    let cancel_bid =
+        gda_client::auctioneer_cancel_bid(&harness.listing, buyer, max_total_price, ORDER_SIZE);
+    process(
+        &mut harness.context,
+        &[place_order, cancel_bid],
+        &[&harness.buyer],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        token_balance(&mut harness.context, &escrow).await,
+        max_total_price
+    );
+
+    // Partial withdrawals leave the rest in escrow.
+    let half = max_total_price / 2;
+    let withdraw = gda_client::auctioneer_withdraw(&harness.auction_house, buyer, half);
+    process(&mut harness.context, &[withdraw], &[&harness.buyer])
+        .await
+        .unwrap();
+    assert_eq!(
+        token_balance(&mut harness.context, &escrow).await,
+        max_total_price - half
+    );
+
+    let withdraw =
+        gda_client::auctioneer_withdraw(&harness.auction_house, buyer, max_total_price - half);
+    process(&mut harness.context, &[withdraw], &[&harness.buyer])
+        .await
+        .unwrap();
+    assert_eq!(token_balance(&mut harness.context, &escrow).await, 0);
+    assert_eq!(
+        treasury_balance(&mut harness, &buyer_account).await,
+        buyer_before
+    );
+}